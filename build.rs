@@ -0,0 +1,496 @@
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+fn main() {
+    // without any rerun directive, cargo would run the script again whenever any file changes
+    println!("cargo:rerun-if-changed=build.rs");
+    let root = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("models");
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    if std::env::var_os("CARGO_FEATURE_EMBEDDED_MODELS").is_some() {
+        embed_models(&root, &out_dir);
+    }
+    if std::env::var_os("CARGO_FEATURE_TYPED_MODELS").is_some() {
+        generate_typed_models(&root, &out_dir);
+    }
+}
+
+//region embedded-models
+/// With the `embedded-models` feature, generate a table of every model definition under
+/// `models/` (including the vendor subdirectories) so they can be compiled into the library.
+fn embed_models(root: &Path, out_dir: &Path) {
+    println!("cargo:rerun-if-changed={}", root.display());
+
+    let mut files: Vec<(String, PathBuf)> = vec![];
+    collect_models(root, root, &mut files);
+    files.sort();
+
+    let mut f = fs::File::create(out_dir.join("embedded_models.rs")).unwrap();
+    writeln!(
+        f,
+        "pub(crate) static EMBEDDED_MODELS: &[(&str, &[u8])] = &["
+    )
+    .unwrap();
+    for (name, path) in files.iter() {
+        writeln!(
+            f,
+            "    ({name:?}, include_bytes!({:?})),",
+            path.display().to_string()
+        )
+        .unwrap();
+    }
+    writeln!(f, "];").unwrap();
+}
+
+fn collect_models(root: &Path, dir: &Path, files: &mut Vec<(String, PathBuf)>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            println!("cargo:rerun-if-changed={}", path.display());
+            collect_models(root, &path, files);
+            continue;
+        }
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        let is_model = (file_name.starts_with("model_") && file_name.ends_with(".json"))
+            || (file_name.starts_with("smdx_") && file_name.ends_with(".xml"));
+        if is_model {
+            // keys are relative to models/ and always use forward slashes
+            let name = path
+                .strip_prefix(root)
+                .unwrap()
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((name, path));
+        }
+    }
+}
+//endregion
+
+//region typed-models
+/// With the `typed-models` feature, generate a struct for every JSON model in `models/`, with
+/// a decoder from the model's registers.  The generated code is included by
+/// `src/lib/typed_models.rs`, which provides the `scale` helper and `Scope` it relies on; points are decoded by
+/// `sunspec_connection::decode`, the same decoders get_point uses.
+fn generate_typed_models(root: &Path, out_dir: &Path) {
+    println!("cargo:rerun-if-changed={}", root.display());
+    let mut files: Vec<(u16, PathBuf)> = fs::read_dir(root)
+        .unwrap()
+        .filter_map(|e| {
+            let path = e.unwrap().path();
+            let name = path.file_name()?.to_str()?;
+            let id = name
+                .strip_prefix("model_")?
+                .strip_suffix(".json")?
+                .parse()
+                .ok()?;
+            Some((id, path))
+        })
+        .collect();
+    files.sort();
+
+    let mut out = String::new();
+    for (id, path) in files.iter() {
+        let model: Value = serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+        let mut gen = ModelGen {
+            id: *id,
+            counts: HashSet::new(),
+            type_names: HashSet::new(),
+            out: &mut out,
+        };
+        collect_counts(&model["group"], &mut gen.counts);
+        gen.group(&model["group"], &format!("Model{id}"), true);
+    }
+    fs::write(out_dir.join("typed_models.rs"), out).unwrap();
+}
+
+struct ModelGen<'a> {
+    id: u16,
+    /// names of points used as group counts, which have to be pushed into the decode scope
+    counts: HashSet<String>,
+    /// type names already used by this model
+    type_names: HashSet<String>,
+    out: &'a mut String,
+}
+
+fn collect_counts(group: &Value, counts: &mut HashSet<String>) {
+    for g in array(&group["groups"]) {
+        if let Some(c) = g["count"].as_str() {
+            counts.insert(c.to_string());
+        }
+        collect_counts(g, counts);
+    }
+}
+
+fn array(v: &Value) -> &[Value] {
+    v.as_array().map(|a| a.as_slice()).unwrap_or_default()
+}
+
+/// `#[doc = ...]` lines from a definition's label, description and units.
+fn doc(v: &Value, indent: &str) -> String {
+    let mut lines = vec![];
+    if let Some(label) = v["label"].as_str() {
+        lines.push(label.to_string());
+    }
+    if let Some(desc) = v["desc"].as_str() {
+        lines.push(desc.to_string());
+    }
+    if let Some(units) = v["units"].as_str() {
+        lines.push(format!("Units: {units}"));
+    }
+    // collapse whitespace, so an indented line in a description doesn't become a doctest
+    lines
+        .iter()
+        .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+        .map(|l| format!("{indent}#[doc = {:?}]\n", format!(" {l}")))
+        .collect::<Vec<_>>()
+        .join(&format!("{indent}#[doc = \"\"]\n"))
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro",
+    "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self", "static",
+    "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// `PhVphA` -> `ph_vph_a`, `W_SF` -> `w_sf`
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut s = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if !c.is_ascii_alphanumeric() {
+            if !s.ends_with('_') {
+                s.push('_');
+            }
+            continue;
+        }
+        if c.is_ascii_uppercase() && i > 0 && !s.ends_with('_') {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_ascii_lowercase());
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_lower)
+            {
+                s.push('_');
+            }
+        }
+        s.push(c.to_ascii_lowercase());
+    }
+    let s = s.trim_matches('_').to_string();
+    if s.is_empty() || s.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("p_{s}")
+    } else if KEYWORDS.contains(&s.as_str()) {
+        format!("{s}_")
+    } else {
+        s
+    }
+}
+
+/// `curve` -> `Curve`, `OUT_OF_SERVICE` -> `OutOfService`, `Volt-VAr` -> `VoltVAr`
+fn camel_case(name: &str) -> String {
+    let mut s = String::new();
+    for part in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = part.chars();
+        let Some(first) = chars.next() else {
+            continue;
+        };
+        s.push(first.to_ascii_uppercase());
+        let rest: String = chars.collect();
+        if part.chars().any(|c| c.is_ascii_lowercase()) {
+            s.push_str(&rest);
+        } else {
+            s.push_str(&rest.to_ascii_lowercase());
+        }
+    }
+    if s.is_empty() || s.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("V{s}")
+    } else {
+        s
+    }
+}
+
+/// Returns `name`, or `name` with a numeric suffix if it's already taken.
+fn unique(name: String, used: &mut HashSet<String>) -> String {
+    let mut candidate = name.clone();
+    let mut n = 2;
+    while !used.insert(candidate.clone()) {
+        candidate = format!("{name}{n}");
+        n += 1;
+    }
+    candidate
+}
+
+/// The Rust type and `decode` function for an unscaled point type.
+fn point_repr(point_type: &str) -> Option<(&'static str, &'static str)> {
+    Some(match point_type {
+        "int16" => ("i16", "int16"),
+        "uint16" | "count" => ("u16", "uint16"),
+        "raw16" => ("u16", "raw16"),
+        "acc16" => ("u16", "acc16"),
+        "sunssf" => ("i16", "int16"),
+        "int32" => ("i32", "int32"),
+        "uint32" => ("u32", "uint32"),
+        "acc32" => ("u32", "acc32"),
+        "int64" => ("i64", "int64"),
+        "uint64" => ("u64", "uint64"),
+        "acc64" => ("u64", "acc64"),
+        "float32" => ("f32", "float32"),
+        "float64" => ("f64", "float64"),
+        "bitfield16" => ("u16", "uint16"),
+        "bitfield32" => ("u32", "uint32"),
+        "bitfield64" => ("u64", "uint64"),
+        "enum16" => ("u16", "uint16"),
+        "enum32" => ("u32", "uint32"),
+        "string" => ("String", "string"),
+        "ipaddr" => ("std::net::Ipv4Addr", "ipaddr"),
+        "ipv6addr" => ("std::net::Ipv6Addr", "ipv6addr"),
+        "eui48" => ("u64", "eui48"),
+        _ => return None,
+    })
+}
+
+enum Field {
+    Point {
+        name: String,
+        ty: String,
+        expr: String,
+        doc: String,
+    },
+    Group {
+        name: String,
+        ty: String,
+        count: Value,
+        len: usize,
+        doc: String,
+    },
+}
+
+impl ModelGen<'_> {
+    fn group(&mut self, group: &Value, type_name: &str, root: bool) {
+        let mut fields: Vec<Field> = vec![];
+        let mut field_names: HashSet<String> = HashSet::new();
+        let mut reads = String::new();
+        let mut scope = String::new();
+        for (idx, p) in array(&group["points"]).iter().enumerate() {
+            let name = p["name"].as_str().unwrap();
+            let point_type = p["type"].as_str().unwrap();
+            let size = p["size"].as_u64().unwrap_or(1);
+            if point_type == "pad" || point_repr(point_type).is_none() {
+                writeln!(reads, "        take(regs, pos, {size})?;").unwrap();
+                continue;
+            }
+            writeln!(reads, "        let r{idx} = take(regs, pos, {size})?;").unwrap();
+            if point_type == "sunssf" {
+                writeln!(
+                    scope,
+                    "        scope.push({name:?}, decode::int16(r{idx}).ok().map(i64::from));"
+                )
+                .unwrap();
+            } else if self.counts.contains(name) {
+                writeln!(
+                    scope,
+                    "        scope.push({name:?}, decode::uint16(r{idx}).ok().map(i64::from));"
+                )
+                .unwrap();
+            }
+            let (ty, helper) = point_repr(point_type).unwrap();
+            let field = unique(snake_case(name), &mut field_names);
+            // an unimplemented string reads as all NULs, which decodes to an empty one
+            let raw = match helper {
+                "string" => format!("decode::string(r{idx}).ok().filter(|s| !s.is_empty())"),
+                _ => format!("decode::{helper}(r{idx}).ok()"),
+            };
+            let numeric = !matches!(
+                point_type,
+                "string" | "ipaddr" | "ipv6addr" | "eui48" | "sunssf"
+            ) && !point_type.starts_with("enum")
+                && !point_type.starts_with("bitfield");
+            let (ty, expr) = match &p["sf"] {
+                Value::String(sf) if numeric => (
+                    "f64".to_string(),
+                    format!("scale({raw}.map(|v| v as f64), scope.get({sf:?}))"),
+                ),
+                Value::Number(sf) if numeric => (
+                    "f64".to_string(),
+                    format!("scale({raw}.map(|v| v as f64), Some({sf}))"),
+                ),
+                _ if point_type.starts_with("enum") && !array(&p["symbols"]).is_empty() => {
+                    let enum_name = self.type_name(&format!("{type_name}{}", camel_case(name)));
+                    self.symbol_enum(&enum_name, ty, p);
+                    (enum_name.clone(), format!("{raw}.map({enum_name}::from)"))
+                }
+                _ => (ty.to_string(), raw),
+            };
+            fields.push(Field::Point {
+                name: field,
+                ty: format!("Option<{ty}>"),
+                expr,
+                doc: doc(p, "    "),
+            });
+        }
+        for g in array(&group["groups"]) {
+            let name = g["name"].as_str().unwrap();
+            let ty = self.type_name(&format!("{type_name}{}", camel_case(name)));
+            self.group(g, &ty, false);
+            let len = array(&g["points"])
+                .iter()
+                .map(|p| p["size"].as_u64().unwrap_or(1) as usize)
+                .sum();
+            fields.push(Field::Group {
+                name: unique(snake_case(name), &mut field_names),
+                ty,
+                count: g.get("count").cloned().unwrap_or(Value::from(1)),
+                len,
+                doc: doc(g, "    "),
+            });
+        }
+
+        let out = &mut *self.out;
+        if root {
+            writeln!(out, "#[doc = \" Model {}\"]", self.id).unwrap();
+            writeln!(out, "#[doc = \"\"]").unwrap();
+        }
+        write!(out, "{}", doc(group, "")).unwrap();
+        writeln!(out, "#[derive(Debug, Clone, PartialEq)]").unwrap();
+        writeln!(out, "pub struct {type_name} {{").unwrap();
+        for f in fields.iter() {
+            match f {
+                Field::Point { name, ty, doc, .. } => {
+                    writeln!(out, "{doc}    pub {name}: {ty},").unwrap();
+                }
+                Field::Group {
+                    name,
+                    ty,
+                    count,
+                    doc,
+                    ..
+                } => {
+                    if count.as_u64() == Some(1) {
+                        writeln!(out, "{doc}    pub {name}: {ty},").unwrap();
+                    } else {
+                        writeln!(out, "{doc}    pub {name}: Vec<{ty}>,").unwrap();
+                    }
+                }
+            }
+        }
+        writeln!(out, "}}\n").unwrap();
+
+        writeln!(out, "impl {type_name} {{").unwrap();
+        writeln!(
+            out,
+            "    fn decode_group(regs: &[u16], pos: &mut usize, scope: &mut Scope) -> Result<Self, TypedModelError> {{"
+        )
+        .unwrap();
+        writeln!(out, "        let mark = scope.mark();").unwrap();
+        write!(out, "{reads}{scope}").unwrap();
+        for f in fields.iter() {
+            match f {
+                Field::Point { name, expr, .. } => {
+                    writeln!(out, "        let f_{name} = {expr};").unwrap();
+                }
+                Field::Group {
+                    name,
+                    ty,
+                    count,
+                    len,
+                    ..
+                } => {
+                    let n = match count {
+                        Value::String(c) => format!(
+                            "scope.get({c:?}).ok_or(TypedModelError::Count({c:?}))? as usize"
+                        ),
+                        // a count of 0 repeats the group to the end of the model
+                        Value::Number(c) if c.as_u64() == Some(0) => {
+                            format!("regs.len().saturating_sub(*pos) / {len}")
+                        }
+                        Value::Number(c) if c.as_u64() == Some(1) => {
+                            writeln!(
+                                out,
+                                "        let f_{name} = {ty}::decode_group(regs, pos, scope)?;"
+                            )
+                            .unwrap();
+                            continue;
+                        }
+                        c => c.to_string(),
+                    };
+                    writeln!(out, "        let f_{name} = (0..{n}).map(|_| {ty}::decode_group(regs, pos, scope)).collect::<Result<Vec<_>, _>>()?;").unwrap();
+                }
+            }
+        }
+        writeln!(out, "        scope.truncate(mark);").unwrap();
+        let names: Vec<String> = fields
+            .iter()
+            .map(|f| match f {
+                Field::Point { name, .. } | Field::Group { name, .. } => {
+                    format!("{name}: f_{name}")
+                }
+            })
+            .collect();
+        writeln!(out, "        Ok(Self {{ {} }})", names.join(", ")).unwrap();
+        writeln!(out, "    }}\n}}\n").unwrap();
+
+        if root {
+            writeln!(out, "impl SunSpecModel for {type_name} {{").unwrap();
+            writeln!(out, "    const ID: u16 = {};", self.id).unwrap();
+            writeln!(
+                out,
+                "    fn decode(regs: &[u16]) -> Result<Self, TypedModelError> {{"
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "        let regs = model_registers(Self::ID, regs)?;\n        Self::decode_group(regs, &mut 0, &mut Scope::default())"
+            )
+            .unwrap();
+            writeln!(out, "    }}\n}}\n").unwrap();
+        }
+    }
+
+    fn type_name(&mut self, name: &str) -> String {
+        unique(name.to_string(), &mut self.type_names)
+    }
+
+    fn symbol_enum(&mut self, enum_name: &str, repr: &str, point: &Value) {
+        // reserved for the catch-all variant
+        let mut variants: HashSet<String> = HashSet::from([String::from("Unknown")]);
+        let mut values: HashSet<u64> = HashSet::new();
+        let mut arms = String::new();
+        let mut defs = String::new();
+        for s in array(&point["symbols"]) {
+            let Some(value) = s["value"].as_u64() else {
+                continue;
+            };
+            // a duplicated value would make the later arm unreachable
+            if !values.insert(value) {
+                continue;
+            }
+            let variant = unique(camel_case(s["name"].as_str().unwrap()), &mut variants);
+            writeln!(defs, "{}    {variant},", doc(s, "    ")).unwrap();
+            writeln!(arms, "            {value} => Self::{variant},").unwrap();
+        }
+        let out = &mut *self.out;
+        write!(out, "{}", doc(point, "")).unwrap();
+        writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq, Eq)]").unwrap();
+        writeln!(
+            out,
+            "pub enum {enum_name} {{\n{defs}    /// A value with no symbol in the model definition"
+        )
+        .unwrap();
+        writeln!(out, "    Unknown({repr}),\n}}\n").unwrap();
+        writeln!(out, "impl From<{repr}> for {enum_name} {{").unwrap();
+        writeln!(out, "    fn from(v: {repr}) -> Self {{\n        match v {{").unwrap();
+        write!(out, "{arms}").unwrap();
+        writeln!(
+            out,
+            "            v => Self::Unknown(v),\n        }}\n    }}\n}}\n"
+        )
+        .unwrap();
+    }
+}
+//endregion
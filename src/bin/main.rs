@@ -29,7 +29,7 @@ pub async fn main() {
         .init();
 
     let socket_addr = "10.174.2.83:502".parse().unwrap();
-    let mut ss = match SunSpecConnection::new(socket_addr, Some(7), false, None).await {
+    let ss = match SunSpecConnection::new(socket_addr, Some(7), false, None).await {
         Ok(mb) => mb,
         Err(e) => {
             error!("Can't create modbus connection: {e}");
@@ -37,12 +37,9 @@ pub async fn main() {
         }
     };
 
-    let ssd = SunSpecData::default();
-    match ss.populate_models(&ssd).await {
-        Ok(m) => ss.models = m,
-        Err(e) => {
-            panic!("Can't populate models: {e}")
-        }
+    let ssd = SunSpecData::new(vec![]);
+    if let Err(e) = ss.populate_models(&ssd).await {
+        panic!("Can't populate models: {e}")
     };
 
     let write = false;
@@ -55,11 +52,10 @@ pub async fn main() {
         // write value
         let _model: u16 = 64206_u16;
         let _field: &str = "XFRTms";
-        let md = ss.models.get(&_model).unwrap().clone();
+        let md = ss.model(_model).unwrap();
         match ss
-            .clone()
             .set_point(
-                md.clone(),
+                &md,
                 PointIdentifier::Point(_field.to_string()),
                 ValueType::Integer(300),
             )
@@ -75,12 +71,12 @@ pub async fn main() {
     } else {
         // read fields
         let _model: u16 = 804_u16;
-        let md = ss.models.get(&_model).unwrap().clone();
+        let md = ss.model(_model).unwrap();
         let _fields: Vec<PointIdentifier> = vec![PointIdentifier::Catalog(
             ".lithium_ion_string.lithium_ion_string_module[1].ModSoH".to_string(),
         )];
         for f in _fields {
-            match ss.clone().get_point(md.clone(), f.clone()).await {
+            match ss.get_point(&md, f.clone()).await {
                 Ok(pt) => {
                     println!("{_model}/{f} = {:#?}", pt.value);
                 }
@@ -11,7 +11,7 @@ use std::convert::TryFrom;
 use sunspec_rs::json::group::{Group, GroupCount};
 use sunspec_rs::json::misc::JSONModel;
 use sunspec_rs::json::point::{Point, PointType, PointValue};
-use sunspec_rs::sunspec_connection::{process_json_group, PointNode};
+use sunspec_rs::sunspec_connection::{process_group, PointNode};
 use sunspec_rs::sunspec_connection::{SunSpecConnection, SunSpecReadError, Word};
 use sunspec_rs::sunspec_data::SunSpecData;
 use sunspec_rs::sunspec_models::{ModelSource, ValueType};
@@ -20,21 +20,16 @@ use tracing_subscriber::{prelude::*, EnvFilter, Registry};
 
 pub async fn setup(addr: &str, slave_id: u8) -> (SunSpecConnection, SunSpecData) {
     let socket_addr = addr.parse().unwrap();
-    let mut ss = match SunSpecConnection::new(socket_addr, Some(slave_id), false, None).await {
+    let ss = match SunSpecConnection::new(socket_addr, Some(slave_id), false, None).await {
         Ok(mb) => mb,
         Err(e) => {
             panic!("Can't create modbus connection: {e}");
         }
     };
 
-    let ssd = SunSpecData::default();
-    match ss.populate_models(&ssd).await {
-        Ok(m) => {
-            ss.models = m;
-        }
-        Err(e) => {
-            panic!("Can't populate models: {e}")
-        }
+    let ssd = SunSpecData::new(vec![]);
+    if let Err(e) = ss.populate_models(&ssd).await {
+        panic!("Can't populate models: {e}")
     };
 
     return (ss, ssd);
@@ -60,5 +55,5 @@ pub async fn main() {
     //endregion
     let addr = "127.0.0.1:8502";
     let (ss, _) = setup(addr, 1).await;
-    info!("{:#?}", ss.catalog);
+    info!("{:#?}", ss.catalog());
 }
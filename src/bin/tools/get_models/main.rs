@@ -16,19 +16,16 @@ pub struct CliArgs {
 
 pub async fn setup(addr: &str, slave_id: u8) -> (SunSpecConnection, SunSpecData) {
     let socket_addr = addr.parse().unwrap();
-    let mut ss = match SunSpecConnection::new(socket_addr, Some(slave_id), false, None).await {
+    let ss = match SunSpecConnection::new(socket_addr, Some(slave_id), false, None).await {
         Ok(mb) => mb,
         Err(e) => {
             panic!("Can't create modbus connection: {e}");
         }
     };
 
-    let ssd = SunSpecData::default();
-    match ss.clone().populate_models(&ssd).await {
-        Ok(m) => ss.models = m,
-        Err(e) => {
-            panic!("Can't populate models: {e}")
-        }
+    let ssd = SunSpecData::new(vec![]);
+    if let Err(e) = ss.populate_models(&ssd).await {
+        panic!("Can't populate models: {e}")
     };
 
     return (ss, ssd);
@@ -41,7 +38,7 @@ pub async fn main() {
         .init();
     let addr = format!("{}:{}", cli.addr, cli.port);
     let (ss, _) = setup(&addr, cli.slave).await;
-    for (id, _) in ss.models.iter() {
+    for (id, _) in ss.models().iter() {
         println!("{}", id);
     }
 }
@@ -0,0 +1,73 @@
+use clap::Parser;
+use std::path::PathBuf;
+use std::process;
+use sunspec_rs::model_import::{apply_import, diff_upstream, FileStatus};
+
+/// Compare the model library with a checkout of the upstream SunSpec models repository, and
+/// optionally import new and changed models.
+#[derive(clap::Parser)]
+pub struct CliArgs {
+    /// root of the upstream models checkout, containing json/ and smdx/
+    pub upstream: PathBuf,
+    /// the model library directory to update
+    #[clap(short, long, default_value = "models")]
+    pub models: PathBuf,
+    /// copy new and changed files into the library; without this, only the report is printed
+    #[clap(long)]
+    pub apply: bool,
+}
+
+pub fn main() {
+    let cli = CliArgs::parse();
+    let report = match diff_upstream(&cli.upstream, &cli.models) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(2);
+        }
+    };
+    for f in report.files.iter() {
+        let status = match f.status {
+            FileStatus::Added => "added",
+            FileStatus::Changed => "changed",
+        };
+        match &f.compared_with {
+            Some(other) => println!("{status}: {} (compared with {other})", f.name),
+            None => println!("{status}: {}", f.name),
+        }
+        // a new model's points are all new; listing them adds nothing to the review
+        if f.status == FileStatus::Changed || f.compared_with.is_some() {
+            for p in f.added_points.iter() {
+                println!("    + {p}");
+            }
+            for p in f.removed_points.iter() {
+                println!("    - {p}");
+            }
+            for p in f.changed_points.iter() {
+                println!("    ~ {}: {}", p.path, p.changes.join(", "));
+            }
+        }
+    }
+    for f in report.local_only.iter() {
+        println!("not upstream: {f}");
+    }
+    for e in report.errors.iter() {
+        println!("error: {e}");
+    }
+    println!(
+        "{} new or changed, {} unchanged, {} not upstream, {} errors",
+        report.files.len(),
+        report.unchanged,
+        report.local_only.len(),
+        report.errors.len()
+    );
+    if cli.apply {
+        match apply_import(&report, &cli.models) {
+            Ok(n) => println!("imported {n} files into {}", cli.models.display()),
+            Err(e) => {
+                eprintln!("{e}");
+                process::exit(2);
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use clap::Parser;
+use std::path::PathBuf;
+use std::process;
+use sunspec_rs::model_validation::{lint_directory, lint_file, FileReport, Severity};
+
+/// Check JSON and SMDX model definitions for structural errors.
+#[derive(clap::Parser)]
+pub struct CliArgs {
+    /// model files, or directories to search for model_<id>.json and smdx_<id>.xml files
+    #[clap(default_value = "models")]
+    pub paths: Vec<PathBuf>,
+    /// only report errors, not warnings
+    #[clap(short, long)]
+    pub quiet: bool,
+}
+
+pub fn main() {
+    let cli = CliArgs::parse();
+    let mut reports: Vec<FileReport> = vec![];
+    for path in cli.paths.iter() {
+        if path.is_dir() {
+            match lint_directory(path) {
+                Ok(r) => reports.extend(r),
+                Err(e) => {
+                    eprintln!("{}: {e}", path.display());
+                    process::exit(2);
+                }
+            }
+        } else {
+            reports.push(lint_file(path));
+        }
+    }
+    let (mut errors, mut warnings) = (0, 0);
+    for report in reports.iter() {
+        for i in report.issues.iter() {
+            match i.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning if cli.quiet => continue,
+                Severity::Warning => warnings += 1,
+            }
+            println!("{}: {i}", report.file.display());
+        }
+    }
+    println!(
+        "{} files checked, {errors} errors, {warnings} warnings",
+        reports.len()
+    );
+    if errors > 0 {
+        process::exit(1);
+    }
+}
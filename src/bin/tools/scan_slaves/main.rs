@@ -2,16 +2,37 @@
 extern crate tracing;
 use clap::Parser;
 use clap_verbosity_flag;
+use std::time::Duration;
+use sunspec_rs::scanner::{
+    ScanOptions, DEFAULT_FIRST_SLAVE, DEFAULT_LAST_SLAVE, DEFAULT_PROBE_TIMEOUT_MS,
+    DEFAULT_SCAN_CONCURRENCY,
+};
 use sunspec_rs::sunspec_connection::SunSpecConnection;
 use sunspec_rs::sunspec_data::SunSpecData;
-use sunspec_rs::sunspec_models::{PointIdentifier, ValueType};
 use tracing_log::AsTrace;
 use tracing_subscriber;
 
+/// Probe a range of modbus slave ids behind one address for SunSpec devices, and print what
+/// was found as JSON.
 #[derive(clap::Parser)]
 pub struct CliArgs {
     pub addr: String,
     pub port: u16,
+    /// the first slave id to probe
+    #[clap(long, default_value_t = DEFAULT_FIRST_SLAVE)]
+    pub first: u8,
+    /// the last slave id to probe, inclusive
+    #[clap(long, default_value_t = DEFAULT_LAST_SLAVE)]
+    pub last: u8,
+    /// how many slaves to probe at once
+    #[clap(long, default_value_t = DEFAULT_SCAN_CONCURRENCY)]
+    pub concurrency: usize,
+    /// how long to wait for each slave to answer, in milliseconds
+    #[clap(long, default_value_t = DEFAULT_PROBE_TIMEOUT_MS)]
+    pub timeout_ms: u64,
+    /// also list devices already found on a lower slave id
+    #[clap(long)]
+    pub duplicates: bool,
     #[clap(flatten)]
     pub verbose: clap_verbosity_flag::Verbosity,
 }
@@ -20,48 +41,37 @@ pub struct CliArgs {
 pub async fn main() {
     let cli = CliArgs::parse();
     tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
         .with_max_level(cli.verbose.log_level_filter().as_trace())
         .init();
     let addr = format!("{}:{}", cli.addr, cli.port);
-    let mut devices: Vec<String> = vec![];
 
-    let ssd = SunSpecData::default();
-    for i in 1..99 {
-        info!("Testing slave {i}");
-        let mut ss = match SunSpecConnection::new(addr.clone(), Some(i), false, None).await {
-            Ok(mb) => mb,
-            Err(e) => {
-                panic!("Can't create modbus connection: {e}");
-            }
-        };
-        ss.models = match ss.clone().populate_models(&ssd).await {
-            Ok(m) => m,
-            Err(_) => {
-                continue;
-            }
-        };
-
-        let md = ss.models.get(&1).unwrap().clone();
-        match ss
-            .clone()
-            .get_point(md.clone(), PointIdentifier::Point("SN".to_string()))
-            .await
-        {
-            Ok(p) => {
-                if let Some(st) = p.value {
-                    if let ValueType::String(s) = st {
-                        if devices.contains(&s) {
-                            warn!("Slave id {i} is a clone signal.");
-                            continue;
-                        }
-                        println!("Slave {i} {s} has {} models.", ss.models.len());
-                        devices.push(s)
-                    }
-                }
-            }
-            Err(e) => {
-                warn!("No serial number in common? (slave {i})  Should not be possible: {e}");
-            }
+    // every probe goes through this one connection, addressed to each slave in turn
+    let conn = match SunSpecConnection::new(addr.clone(), None, false, None).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            error!("Can't connect to {addr}: {e}");
+            std::process::exit(1);
+        }
+    };
+    let ssd = SunSpecData::new(vec![]);
+    // every slave shares the same definitions, so load them all up front
+    ssd.preload();
+    let options = ScanOptions::builder()
+        .first_slave(cli.first)
+        .last_slave(cli.last)
+        .concurrency(cli.concurrency)
+        .probe_timeout(Duration::from_millis(cli.timeout_ms))
+        .build();
+    let mut results = conn.scan_slaves(&ssd, &options).await;
+    if !cli.duplicates {
+        results.retain(|r| r.duplicate_of.is_none());
+    }
+    match serde_json::to_string_pretty(&results) {
+        Ok(json) => println!("{json}"),
+        Err(e) => {
+            error!("Can't serialize scan results: {e}");
+            std::process::exit(1);
         }
     }
 }
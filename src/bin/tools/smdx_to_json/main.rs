@@ -0,0 +1,72 @@
+#[macro_use]
+extern crate tracing;
+use clap::Parser;
+use std::path::PathBuf;
+use std::process;
+use sunspec_rs::json::misc::JSONModel;
+use sunspec_rs::model_validation::validate_json_model;
+use sunspec_rs::sunspec_models::SmdxFile;
+use tracing_log::AsTrace;
+
+/// Convert SMDX model definitions to the JSON model format.
+#[derive(clap::Parser)]
+pub struct CliArgs {
+    /// SMDX files to convert
+    #[clap(required = true)]
+    pub inputs: Vec<PathBuf>,
+    /// write model_<id>.json files into this directory instead of printing to stdout
+    #[clap(short, long)]
+    pub out_dir: Option<PathBuf>,
+    #[clap(flatten)]
+    pub verbose: clap_verbosity_flag::Verbosity,
+}
+
+fn convert(input: &PathBuf) -> anyhow::Result<JSONModel> {
+    let fd = std::fs::File::open(input)?;
+    let smdx: SmdxFile = match serde_xml_rs::from_reader(fd) {
+        Ok(m) => m,
+        Err(e) => anyhow::bail!("Couldn't deserialize xml: {e}"),
+    };
+    match JSONModel::try_from(&smdx) {
+        Ok(m) => Ok(m),
+        Err(e) => anyhow::bail!("Couldn't convert model {}: {e}", smdx.model.id),
+    }
+}
+
+#[tokio::main]
+pub async fn main() {
+    let cli = CliArgs::parse();
+    tracing_subscriber::fmt()
+        .with_max_level(cli.verbose.log_level_filter().as_trace())
+        .init();
+    let mut failed = false;
+    for input in cli.inputs.iter() {
+        let model = match convert(input) {
+            Ok(m) => m,
+            Err(e) => {
+                error!("{}: {e}", input.display());
+                failed = true;
+                continue;
+            }
+        };
+        for i in validate_json_model(&model) {
+            warn!("{}: {i}", input.display());
+        }
+        let json = serde_json::to_string_pretty(&model).unwrap();
+        match &cli.out_dir {
+            Some(dir) => {
+                let out = dir.join(format!("model_{}.json", model.id));
+                if let Err(e) = std::fs::write(&out, json + "\n") {
+                    error!("Can't write {}: {e}", out.display());
+                    failed = true;
+                    continue;
+                }
+                info!("{} -> {}", input.display(), out.display());
+            }
+            None => println!("{json}"),
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
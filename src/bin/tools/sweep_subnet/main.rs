@@ -0,0 +1,70 @@
+#[macro_use]
+extern crate tracing;
+use clap::Parser;
+use std::time::Duration;
+use sunspec_rs::scanner::DEFAULT_PROBE_TIMEOUT_MS;
+use sunspec_rs::subnet::{
+    sweep_subnet, SweepOptions, DEFAULT_CONNECT_TIMEOUT_MS, DEFAULT_MODBUS_PORT,
+    DEFAULT_SWEEP_CONCURRENCY, DEFAULT_UNIT_IDS,
+};
+use sunspec_rs::sunspec_data::SunSpecData;
+use tracing_log::AsTrace;
+
+/// Sweep an IPv4 range for Modbus/TCP listeners, probe them for SunSpec devices, and print
+/// what was found as JSON.
+#[derive(clap::Parser)]
+pub struct CliArgs {
+    /// the range to sweep, e.g. 192.168.1.0/24
+    pub cidr: String,
+    /// a TCP port to try on every host; may be given more than once
+    #[clap(long = "port", default_values_t = [DEFAULT_MODBUS_PORT])]
+    pub ports: Vec<u16>,
+    /// a unit id to probe on every listener; may be given more than once
+    #[clap(long = "unit-id", default_values_t = DEFAULT_UNIT_IDS)]
+    pub unit_ids: Vec<u8>,
+    /// how many host and port pairs to try at once
+    #[clap(long, default_value_t = DEFAULT_SWEEP_CONCURRENCY)]
+    pub concurrency: usize,
+    /// how long to wait for a TCP connection, in milliseconds
+    #[clap(long, default_value_t = DEFAULT_CONNECT_TIMEOUT_MS)]
+    pub connect_timeout_ms: u64,
+    /// how long to wait for each unit id to answer, in milliseconds
+    #[clap(long, default_value_t = DEFAULT_PROBE_TIMEOUT_MS)]
+    pub timeout_ms: u64,
+    #[clap(flatten)]
+    pub verbose: clap_verbosity_flag::Verbosity,
+}
+
+#[tokio::main]
+pub async fn main() {
+    let cli = CliArgs::parse();
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(cli.verbose.log_level_filter().as_trace())
+        .init();
+
+    let ssd = SunSpecData::new(vec![]);
+    // every device shares the same definitions, so load them all up front
+    ssd.preload();
+    let options = SweepOptions::builder()
+        .ports(cli.ports)
+        .unit_ids(cli.unit_ids)
+        .concurrency(cli.concurrency)
+        .connect_timeout(Duration::from_millis(cli.connect_timeout_ms))
+        .probe_timeout(Duration::from_millis(cli.timeout_ms))
+        .build();
+    let results = match sweep_subnet(&cli.cidr, &ssd, &options).await {
+        Ok(results) => results,
+        Err(e) => {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    };
+    match serde_json::to_string_pretty(&results) {
+        Ok(json) => println!("{json}"),
+        Err(e) => {
+            error!("Can't serialize sweep results: {e}");
+            std::process::exit(1);
+        }
+    }
+}
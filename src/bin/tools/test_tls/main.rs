@@ -41,7 +41,7 @@ pub async fn main() {
         .build();
 
     let socket_addr = "127.0.0.1:8502".parse().unwrap();
-    let mut ss = match SunSpecConnection::new(socket_addr, Some(1), false, Some(tls)).await {
+    let ss = match SunSpecConnection::new(socket_addr, Some(1), false, Some(tls)).await {
         Ok(mb) => mb,
         Err(e) => {
             error!("Can't create modbus connection: {e}");
@@ -49,21 +49,18 @@ pub async fn main() {
         }
     };
 
-    let ssd = SunSpecData::default();
-    match ss.populate_models(&ssd).await {
-        Ok(m) => ss.models = m,
-        Err(e) => {
-            panic!("Can't populate models: {e}")
-        }
+    let ssd = SunSpecData::new(vec![]);
+    if let Err(e) = ss.populate_models(&ssd).await {
+        panic!("Can't populate models: {e}")
     };
 
     // read fields
     let _model: u16 = 701_u16;
-    let md = ss.models.get(&_model).unwrap().clone();
+    let md = ss.model(_model).unwrap();
     let _fields: Vec<PointIdentifier> =
         vec![PointIdentifier::Catalog(".DERMeasureAC.ACType".to_string())];
     for f in _fields {
-        match ss.clone().get_point(md.clone(), f.clone()).await {
+        match ss.get_point(&md, f.clone()).await {
             Ok(pt) => {
                 println!("{_model}/{f} = {:#?}", pt.value);
             }
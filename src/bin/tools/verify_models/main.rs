@@ -0,0 +1,49 @@
+use clap::Parser;
+use std::path::PathBuf;
+use std::process;
+use sunspec_rs::model_manifest::verify_manifest;
+
+/// Verify a model library against the md5 sums in its manifest.xml.
+#[derive(clap::Parser)]
+pub struct CliArgs {
+    /// the model library directory
+    #[clap(default_value = "models")]
+    pub dir: PathBuf,
+    /// manifest to check against; defaults to manifest.xml in the library directory
+    #[clap(short, long)]
+    pub manifest: Option<PathBuf>,
+}
+
+pub fn main() {
+    let cli = CliArgs::parse();
+    let manifest = cli
+        .manifest
+        .clone()
+        .unwrap_or_else(|| cli.dir.join("manifest.xml"));
+    let report = match verify_manifest(&cli.dir, &manifest) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(2);
+        }
+    };
+    for f in report.missing.iter() {
+        println!("missing: {f}");
+    }
+    for f in report.modified.iter() {
+        println!("modified: {f}");
+    }
+    for f in report.extra.iter() {
+        println!("extra: {f}");
+    }
+    println!(
+        "{} files checked, {} missing, {} modified, {} extra",
+        report.checked,
+        report.missing.len(),
+        report.modified.len(),
+        report.extra.len()
+    );
+    if !report.is_ok() {
+        process::exit(1);
+    }
+}
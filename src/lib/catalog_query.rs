@@ -0,0 +1,340 @@
+use crate::poller::{coalesce, DEFAULT_MAX_BATCH, DEFAULT_MAX_GAP};
+use crate::sunspec_connection::{
+    decode_point_value, point_len, SunSpecConnection, SunSpecReadError,
+};
+use crate::sunspec_models::{Point, ValueType};
+use std::fmt::Display;
+use std::str::FromStr;
+use thiserror::Error;
+use tokio_modbus::Address;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SunSpecQueryError {
+    #[error("Catalog query must start with '.': {0}")]
+    MissingLeadingDot(String),
+    #[error("Empty path segment in catalog query: {0}")]
+    EmptySegment(String),
+    #[error("Invalid group index in catalog query: {0}")]
+    InvalidIndex(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum IndexPattern {
+    /// no index, i.e. a group that isn't repeated
+    None,
+    /// `[*]`: any index, or no index at all
+    Any,
+    /// `[n]`
+    Exact(u16),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    name: String,
+    index: IndexPattern,
+}
+
+/// A pattern over catalog paths, e.g. `.lithium_ion_string.lithium_ion_string_module[*].ModSoH`.
+///
+/// Each dot-separated segment matches one level of the catalog path.  Names may contain `*`
+/// wildcards (`.DERMeasureAC.*` or `.*.W`), and `[*]` matches every instance of a repeated group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogQuery {
+    pattern: String,
+    segments: Vec<Segment>,
+}
+
+/// A catalog entry selected by a CatalogQuery.  Values are unscaled, as stored in the catalog;
+/// scale them with the point's scale factor, or read the point with get_point instead.
+#[derive(Debug, Clone)]
+pub struct CatalogMatch {
+    pub path: String,
+    pub value: ValueType,
+    pub address: Address,
+    pub point: Point,
+}
+
+/// The outcome of re-reading the points a CatalogQuery selected.
+#[derive(Debug, Default)]
+pub struct QueryReport {
+    /// the points read, ordered by address, with their fresh unscaled values
+    pub matches: Vec<CatalogMatch>,
+    /// the catalog path of every point that couldn't be read or decoded, and why, ordered by
+    /// address.  Points the device reports as not implemented are here too.
+    pub errors: Vec<(String, SunSpecReadError)>,
+}
+
+impl CatalogQuery {
+    pub fn parse(pattern: &str) -> Result<Self, SunSpecQueryError> {
+        let rest = match pattern.strip_prefix('.') {
+            Some(r) => r,
+            None => return Err(SunSpecQueryError::MissingLeadingDot(pattern.to_string())),
+        };
+        let mut segments: Vec<Segment> = vec![];
+        for raw in rest.split('.') {
+            let (name, index) = match split_index(raw) {
+                Some((name, idx)) => (name, idx),
+                None => return Err(SunSpecQueryError::InvalidIndex(pattern.to_string())),
+            };
+            if name.is_empty() {
+                return Err(SunSpecQueryError::EmptySegment(pattern.to_string()));
+            }
+            let index = match index {
+                None => IndexPattern::None,
+                Some("*") => IndexPattern::Any,
+                Some(i) => match i.parse::<u16>() {
+                    Ok(i) => IndexPattern::Exact(i),
+                    Err(_) => return Err(SunSpecQueryError::InvalidIndex(pattern.to_string())),
+                },
+            };
+            segments.push(Segment {
+                name: name.to_string(),
+                index,
+            });
+        }
+        Ok(CatalogQuery {
+            pattern: pattern.to_string(),
+            segments,
+        })
+    }
+
+    /// Returns true if the given catalog path is selected by this query.
+    pub fn matches(&self, path: &str) -> bool {
+        let rest = match path.strip_prefix('.') {
+            Some(r) => r,
+            None => return false,
+        };
+        let parts: Vec<&str> = rest.split('.').collect();
+        if parts.len() != self.segments.len() {
+            return false;
+        }
+        parts.iter().zip(self.segments.iter()).all(|(part, seg)| {
+            let (name, index) = match split_index(part) {
+                Some(p) => p,
+                None => return false,
+            };
+            let index_ok = match (&seg.index, index) {
+                (IndexPattern::Any, _) => true,
+                (IndexPattern::None, None) => true,
+                (IndexPattern::Exact(want), Some(i)) => i.parse::<u16>().ok() == Some(*want),
+                _ => false,
+            };
+            index_ok && glob_match(&seg.name, name)
+        })
+    }
+}
+
+impl FromStr for CatalogQuery {
+    type Err = SunSpecQueryError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CatalogQuery::parse(s)
+    }
+}
+
+impl Display for CatalogQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+impl SunSpecConnection {
+    /// Select entries from the cached point catalog, ordered by address.  Values are unscaled,
+    /// and only points that could be decoded when the catalog was built are in it.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - the pattern to match catalog paths against
+    pub fn query_catalog(&self, query: &CatalogQuery) -> Vec<CatalogMatch> {
+        let mut matches: Vec<CatalogMatch> = self
+            .catalog
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| query.matches(path))
+            .map(|(path, pn)| CatalogMatch {
+                path: path.clone(),
+                value: pn.value.clone(),
+                address: pn.address,
+                point: pn.point_data.clone(),
+            })
+            .collect();
+        matches.sort_by(|a, b| (a.address, &a.path).cmp(&(b.address, &b.path)));
+        matches
+    }
+
+    /// Select entries from the point catalog and re-read them from the device, using as few
+    /// register reads as possible.  The catalog is updated with the fresh values, which are
+    /// unscaled.  A point that can't be read or decoded, including one the device reports as
+    /// not implemented, is reported in the errors with its path, and keeps its catalog value.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - the pattern to match catalog paths against
+    pub async fn query_points(&self, query: &CatalogQuery) -> QueryReport {
+        let selected = self.query_catalog(query);
+        let wanted: Vec<(Address, u16)> = selected
+            .iter()
+            .map(|m| (m.address, point_len(&m.point).unwrap_or(1)))
+            .collect();
+        let mut blocks: Vec<(Address, u16, Result<Vec<u16>, SunSpecReadError>)> = vec![];
+        for (start, len) in coalesce(wanted, DEFAULT_MAX_GAP, DEFAULT_MAX_BATCH) {
+            blocks.push((start, len, self.get_raw(start, len).await));
+        }
+        let mut report = QueryReport::default();
+        for mut m in selected {
+            let len = point_len(&m.point).unwrap_or(1);
+            let block = blocks.iter().find(|(start, block_len, _)| {
+                m.address >= *start && m.address + len <= *start + *block_len
+            });
+            let decoded = match block {
+                Some((start, _, Ok(data))) => {
+                    let offset = (m.address - *start) as usize;
+                    match data.get(offset..offset + len as usize) {
+                        Some(data) => decode_point_value(&m.point, data, self.strict_symbol),
+                        None => Err(SunSpecReadError::OtherError(format!(
+                            "short read of the block at {start}"
+                        ))),
+                    }
+                }
+                Some((_, _, Err(e))) => Err(e.clone()),
+                None => Err(SunSpecReadError::OtherError(String::from(
+                    "no registers read",
+                ))),
+            };
+            match decoded {
+                Ok(v) => {
+                    m.value = v;
+                    report.matches.push(m);
+                }
+                Err(e) => {
+                    debug!("{}: {e}", m.path);
+                    report.errors.push((m.path, e));
+                }
+            }
+        }
+        let mut catalog = self.catalog.write().unwrap();
+        for m in report.matches.iter() {
+            if let Some(pn) = catalog.get_mut(&m.path) {
+                pn.value = m.value.clone();
+            }
+        }
+        report
+    }
+}
+
+/// split `name[idx]` into its name and index; returns None for a malformed index
+fn split_index(segment: &str) -> Option<(&str, Option<&str>)> {
+    match segment.find('[') {
+        None => Some((segment, None)),
+        Some(open) => {
+            let idx = segment[open + 1..].strip_suffix(']')?;
+            Some((&segment[..open], Some(idx)))
+        }
+    }
+}
+
+/// match a name against a pattern where `*` matches any run of characters
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+    let mut rest = match name.strip_prefix(parts[0]) {
+        Some(r) => r,
+        None => return false,
+    };
+    let last = parts[parts.len() - 1];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[test]
+fn test_catalog_query_matches() {
+    let q = CatalogQuery::parse(".lithium_ion_string.lithium_ion_string_module[*].ModSoH").unwrap();
+    assert!(q.matches(".lithium_ion_string.lithium_ion_string_module[1].ModSoH"));
+    assert!(q.matches(".lithium_ion_string.lithium_ion_string_module[12].ModSoH"));
+    assert!(q.matches(".lithium_ion_string.lithium_ion_string_module.ModSoH"));
+    assert!(!q.matches(".lithium_ion_string.lithium_ion_string_module[1].ModSoC"));
+    assert!(!q.matches(".lithium_ion_string.ModSoH"));
+
+    let q = CatalogQuery::parse(".DERVoltVar.Crv[2].Pt[*].V").unwrap();
+    assert!(q.matches(".DERVoltVar.Crv[2].Pt[4].V"));
+    assert!(!q.matches(".DERVoltVar.Crv[1].Pt[4].V"));
+
+    let q = CatalogQuery::parse(".*.Mod*").unwrap();
+    assert!(q.matches(".lithium_ion_string.ModSoH"));
+    assert!(!q.matches(".lithium_ion_string.StrSoH"));
+}
+
+#[test]
+fn test_catalog_query_parse_errors() {
+    assert_eq!(
+        CatalogQuery::parse("DERMeasureAC.W"),
+        Err(SunSpecQueryError::MissingLeadingDot(String::from(
+            "DERMeasureAC.W"
+        )))
+    );
+    assert!(CatalogQuery::parse(".DERVoltVar..V").is_err());
+    assert!(CatalogQuery::parse(".DERVoltVar.Crv[x].V").is_err());
+}
+
+#[tokio::test]
+async fn test_query_points_errors() {
+    use crate::json::point::PointType;
+    use crate::modbus_test_harness::ModbusTestHarness;
+    use crate::sunspec_connection::PointNode;
+    use crate::sunspec_data::SunSpecData;
+    use std::collections::HashSet;
+
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: vec![0],
+            failing: HashSet::from([40500]),
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    conn.populate_models(&SunSpecData::default()).await.unwrap();
+    // a point out where the device won't answer, and a device address it no longer implements
+    conn.catalog.write().unwrap().insert(
+        String::from(".common.Unreadable"),
+        PointNode {
+            value: ValueType::Integer(1),
+            address: 40500,
+            point_data: Point {
+                id: String::from("Unreadable"),
+                r#type: PointType::Uint16,
+                ..Default::default()
+            },
+        },
+    );
+    conn.set_u16(40068, 0xffff).await.unwrap();
+
+    let report = conn
+        .query_points(&CatalogQuery::parse(".common.*").unwrap())
+        .await;
+    let paths: Vec<&str> = report.matches.iter().map(|m| m.path.as_str()).collect();
+    assert!(paths.contains(&".common.SN"));
+    assert_eq!(report.errors.len(), 2);
+    assert_eq!(
+        report.errors[0],
+        (
+            String::from(".common.DA"),
+            SunSpecReadError::DatapointNotImplemented
+        )
+    );
+    assert_eq!(report.errors[1].0, ".common.Unreadable");
+    assert!(matches!(report.errors[1].1, SunSpecReadError::CommError(_)));
+    // a failed read leaves the cached value alone
+    assert_eq!(
+        conn.catalog_entry(".common.DA").unwrap().value,
+        ValueType::Integer(0)
+    );
+}
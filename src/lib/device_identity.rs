@@ -0,0 +1,339 @@
+use crate::sunspec_connection::{SunSpecConnection, SunSpecReadError};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::timeout;
+use tokio_modbus::{Address, Request, Response, Slave};
+
+/// The id of the common model, which every SunSpec device exposes first.
+pub const COMMON_MODEL_ID: u16 = 1;
+
+// offsets of the common model's points from its ID register, and their lengths in registers.
+// The layout is fixed by the spec, so the identity can be read even without a model definition.
+const MN_OFFSET: u16 = 2;
+const MD_OFFSET: u16 = 18;
+const OPT_OFFSET: u16 = 34;
+const VR_OFFSET: u16 = 42;
+const SN_OFFSET: u16 = 50;
+const DA_OFFSET: u16 = 66;
+const LONG_STRING_LEN: u16 = 16;
+const SHORT_STRING_LEN: u16 = 8;
+
+/// Encapsulated interface transport, the function code carrying Read Device Identification.
+pub const MEI_FUNCTION_CODE: u8 = 0x2B;
+/// The MEI type of Read Device Identification.
+pub const MEI_READ_DEVICE_ID: u8 = 0x0E;
+// read device id code for the basic objects, and the ids of those objects
+const READ_BASIC_DEVICE_ID: u8 = 0x01;
+const VENDOR_NAME_OBJECT: u8 = 0x00;
+const PRODUCT_CODE_OBJECT: u8 = 0x01;
+const REVISION_OBJECT: u8 = 0x02;
+const MORE_FOLLOWS: u8 = 0xFF;
+
+/// Who a device is, as reported by its common model (model 1).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceIdentity {
+    pub manufacturer: String,
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub serial_number: String,
+    /// the modbus device address the device reports for itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_address: Option<u16>,
+}
+
+/// The basic objects of Modbus Read Device Identification (function 0x2B, MEI type 0x0E),
+/// which some devices answer even when their common model is broken.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceIdentification {
+    pub vendor_name: String,
+    pub product_code: String,
+    pub revision: String,
+}
+
+impl From<DeviceIdentification> for DeviceIdentity {
+    fn from(mei: DeviceIdentification) -> Self {
+        DeviceIdentity {
+            manufacturer: mei.vendor_name,
+            model: mei.product_code,
+            version: optional(mei.revision),
+            ..Default::default()
+        }
+    }
+}
+
+impl DeviceIdentity {
+    /// Whether two identities describe the same physical device, e.g. one answering on several
+    /// slave ids.  Firmware version and device address are ignored, as they aren't fixed.
+    /// Without a serial number there's no telling devices of the same model apart, so an
+    /// identity without one never matches.
+    pub fn is_same_device(&self, other: &DeviceIdentity) -> bool {
+        !self.serial_number.is_empty()
+            && self.manufacturer == other.manufacturer
+            && self.model == other.model
+            && self.serial_number == other.serial_number
+    }
+
+    /// Whether the common model left out the manufacturer or model, as broken ones do.
+    pub fn is_partial(&self) -> bool {
+        self.manufacturer.is_empty() || self.model.is_empty()
+    }
+
+    /// Fill in whatever the common model left empty from the device identification objects.
+    pub fn fill_from(&mut self, mei: &DeviceIdentification) {
+        if self.manufacturer.is_empty() {
+            self.manufacturer = mei.vendor_name.clone();
+        }
+        if self.model.is_empty() {
+            self.model = mei.product_code.clone();
+        }
+        if self.version.is_none() {
+            self.version = optional(mei.revision.clone());
+        }
+    }
+}
+
+impl SunSpecConnection {
+    /// Read the device's identity from its common model.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - the address of the common model's ID register, e.g. 40002
+    pub async fn read_identity(
+        &self,
+        address: Address,
+    ) -> Result<DeviceIdentity, SunSpecReadError> {
+        let device_address = match self.get_u16(address + DA_OFFSET).await {
+            Ok(da) => Some(da),
+            Err(SunSpecReadError::DatapointNotImplemented) => None,
+            Err(e) => return Err(e),
+        };
+        Ok(DeviceIdentity {
+            manufacturer: self.get_text(address + MN_OFFSET, LONG_STRING_LEN).await?,
+            model: self.get_text(address + MD_OFFSET, LONG_STRING_LEN).await?,
+            options: optional(
+                self.get_text(address + OPT_OFFSET, SHORT_STRING_LEN)
+                    .await?,
+            ),
+            version: optional(self.get_text(address + VR_OFFSET, SHORT_STRING_LEN).await?),
+            serial_number: self.get_text(address + SN_OFFSET, LONG_STRING_LEN).await?,
+            device_address,
+        })
+    }
+
+    /// Read the basic device identification objects: vendor name, product code and revision.
+    /// This doesn't depend on the SunSpec models at all, so it works on devices whose common
+    /// model is missing or broken.  Many devices and gateways never answer it, so a timeout
+    /// reconnects rather than leave a late reply on the socket for the next request.
+    ///
+    /// # Arguments
+    ///
+    /// * `wait` - how long to wait for each response
+    pub async fn read_device_identification(
+        &self,
+        wait: Duration,
+    ) -> Result<DeviceIdentification, SunSpecReadError> {
+        let mut objects: HashMap<u8, String> = HashMap::new();
+        let mut ctx = self.ctx.lock().await;
+        ctx.set_slave(self.slave_num.map_or(Slave::tcp_device(), Slave));
+        let mut next = VENDOR_NAME_OBJECT;
+        loop {
+            let request = Request::Custom(
+                MEI_FUNCTION_CODE,
+                Cow::Owned(vec![MEI_READ_DEVICE_ID, READ_BASIC_DEVICE_ID, next]),
+            );
+            let response = match timeout(wait, ctx.call(request)).await {
+                Ok(Ok(response)) => response,
+                Ok(Err(e)) => return Err(SunSpecReadError::CommError(e.to_string())),
+                Err(e) => {
+                    if let Err(e) = self.reconnect(&mut ctx).await {
+                        warn!(
+                            "[{}] can't reconnect after device identification timed out: {e}",
+                            self.addr
+                        );
+                    }
+                    return Err(SunSpecReadError::CommError(e.to_string()));
+                }
+            };
+            let Response::Custom(MEI_FUNCTION_CODE, data) = response else {
+                return Err(SunSpecReadError::OtherError(format!(
+                    "unexpected response to read device identification: {response:?}"
+                )));
+            };
+            // a device may split the objects over several responses; only follow it forwards
+            match parse_device_identification(&data, &mut objects)? {
+                Some(id) if id > next => next = id,
+                _ => break,
+            }
+        }
+        Ok(DeviceIdentification {
+            vendor_name: objects.remove(&VENDOR_NAME_OBJECT).unwrap_or_default(),
+            product_code: objects.remove(&PRODUCT_CODE_OBJECT).unwrap_or_default(),
+            revision: objects.remove(&REVISION_OBJECT).unwrap_or_default(),
+        })
+    }
+
+    /// Returns the identity found by the last model discovery, if the device has a common model.
+    pub fn identity(&self) -> Option<DeviceIdentity> {
+        self.identity.read().unwrap().clone()
+    }
+
+    /// get_string without the NUL padding
+    async fn get_text(&self, addr: Address, quantity: u16) -> Result<String, SunSpecReadError> {
+        let s = self.get_string(addr, quantity).await?;
+        Ok(s.trim_matches(char::from(0)).trim().to_string())
+    }
+}
+
+/// Collect the objects of one Read Device Identification response (the bytes after the function
+/// code), returning the id of the next object if more follow.
+fn parse_device_identification(
+    data: &[u8],
+    objects: &mut HashMap<u8, String>,
+) -> Result<Option<u8>, SunSpecReadError> {
+    let malformed =
+        || SunSpecReadError::OtherError("malformed device identification response".to_string());
+    // MEI type, read device id code, conformity level, more follows, next object id, count
+    if data.len() < 6 || data[0] != MEI_READ_DEVICE_ID {
+        return Err(malformed());
+    }
+    let mut rest = &data[6..];
+    for _ in 0..data[5] {
+        let (id, len) = match rest {
+            [id, len, ..] => (*id, *len as usize),
+            _ => return Err(malformed()),
+        };
+        let value = rest.get(2..2 + len).ok_or_else(malformed)?;
+        let value = String::from_utf8_lossy(value);
+        objects.insert(id, value.trim_matches(char::from(0)).trim().to_string());
+        rest = &rest[2 + len..];
+    }
+    Ok((data[3] == MORE_FOLLOWS).then_some(data[4]))
+}
+
+/// unimplemented optional strings read as all NULs
+fn optional(s: String) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(s)
+    }
+}
+
+#[tokio::test]
+async fn test_device_identity() {
+    use crate::modbus_test_harness::ModbusTestHarness;
+    use crate::sunspec_data::SunSpecData;
+
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: vec![0],
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(conn.identity().is_none());
+    conn.populate_models(&SunSpecData::default()).await.unwrap();
+    let identity = conn.identity().unwrap();
+    assert_eq!(identity.manufacturer, "Test");
+    assert_eq!(identity.model, "TestHarness");
+    assert_eq!(identity.serial_number, "1234567890");
+    assert_eq!(identity.options, None);
+    assert_eq!(identity.device_address, Some(0));
+
+    let json = serde_json::to_string(&identity).unwrap();
+    assert!(!json.contains("options"));
+    let parsed: DeviceIdentity = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, identity);
+
+    // the same device seen through another slave id, running newer firmware
+    let other = DeviceIdentity {
+        version: Some("2.0".to_string()),
+        device_address: Some(2),
+        ..identity.clone()
+    };
+    assert!(identity.is_same_device(&other));
+    let other = DeviceIdentity {
+        serial_number: "0987654321".to_string(),
+        ..identity.clone()
+    };
+    assert!(!identity.is_same_device(&other));
+    // without serial numbers, devices can't be told apart
+    let anonymous = DeviceIdentity {
+        serial_number: String::new(),
+        ..identity.clone()
+    };
+    assert!(!anonymous.is_same_device(&anonymous.clone()));
+}
+
+#[tokio::test]
+async fn test_device_identification() {
+    use crate::modbus_test_harness::ModbusTestHarness;
+
+    // the harness splits the objects over two responses
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: vec![0],
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    let mei = conn
+        .read_device_identification(Duration::from_millis(100))
+        .await
+        .unwrap();
+    assert_eq!(
+        mei,
+        DeviceIdentification {
+            vendor_name: "TestVendor".to_string(),
+            product_code: "TH-1".to_string(),
+            revision: "1.0".to_string(),
+        }
+    );
+
+    let mut objects: HashMap<u8, String> = HashMap::new();
+    // an object claiming more bytes than the response holds
+    let truncated = [0x0E, 0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x05, b'A'];
+    assert!(parse_device_identification(&truncated, &mut objects).is_err());
+    assert!(parse_device_identification(&[0x0E, 0x01], &mut objects).is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_device_identification_timeout() {
+    use crate::discovery::{DiscoveryLimits, DEFAULT_IDENTIFICATION_TIMEOUT_MS};
+    use crate::modbus_test_harness::ModbusTestHarness;
+    use crate::sunspec_connection::DEFAULT_NETWORK_TIMEOUT_MS;
+    use crate::sunspec_data::SunSpecData;
+
+    // a common model without a manufacturer, on a device slow to answer the fallback
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: vec![0],
+            registers: (40004..40020).map(|addr| (addr, 0)).collect(),
+            slow_identification: true,
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    let started = tokio::time::Instant::now();
+    let report = conn
+        .discover_models(&SunSpecData::default(), &DiscoveryLimits::default())
+        .await;
+    assert!(started.elapsed() < Duration::from_millis(DEFAULT_NETWORK_TIMEOUT_MS));
+    assert!(started.elapsed() >= Duration::from_millis(DEFAULT_IDENTIFICATION_TIMEOUT_MS));
+    // the late reply doesn't answer the reads discovery goes on with
+    assert!(report.complete, "{:?}", report.errors);
+    let identity = report.identity.unwrap();
+    assert_eq!(identity.manufacturer, "");
+    assert_eq!(identity.model, "TestHarness");
+}
@@ -0,0 +1,396 @@
+use crate::device_identity::{DeviceIdentity, COMMON_MODEL_ID};
+use crate::model_data::ModelData;
+use crate::sunspec_connection::{
+    process_group, PointNode, SunSpecConnection, SunSpecGroupError, SunSpecReadError, ADDR_OFFSET,
+    SUNSPEC_END_MODEL_ID,
+};
+use crate::sunspec_data::SunSpecData;
+use bon::Builder;
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+use tokio_modbus::Address;
+
+/// Where the model chain starts: the register after the `SunS` marker.
+pub const SUNSPEC_FIRST_MODEL_ADDRESS: Address = 40002;
+/// Most models discovery will walk before giving up on finding the end marker.
+pub const DEFAULT_MAX_MODELS: usize = 128;
+pub const DEFAULT_IDENTIFICATION_TIMEOUT_MS: u64 = 1_000_u64;
+
+/// Limits that keep discovery from walking off into garbage when a device's end marker is
+/// missing or its model lengths are corrupt, or waiting long on requests it can do without.
+#[derive(Debug, Clone, Builder)]
+pub struct DiscoveryLimits {
+    #[builder(default = DEFAULT_MAX_MODELS)]
+    pub max_models: usize,
+    /// the last register a model may occupy
+    #[builder(default = Address::MAX)]
+    pub max_address: Address,
+    /// how long to wait for Read Device Identification, the fallback for a missing or partial
+    /// common model, which many devices never answer
+    #[builder(default = Duration::from_millis(DEFAULT_IDENTIFICATION_TIMEOUT_MS))]
+    pub identification_timeout: Duration,
+}
+
+impl Default for DiscoveryLimits {
+    fn default() -> Self {
+        DiscoveryLimits::builder().build()
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum DiscoveryError {
+    #[error("Can't read the model id at {0}: {1}")]
+    IdRead(Address, SunSpecReadError),
+    #[error("Can't read the length of model {1} at {0}: {2}")]
+    LengthRead(Address, u16, SunSpecReadError),
+    #[error("Invalid model id 0 at {0}")]
+    InvalidId(Address),
+    #[error("Can't read the device identity from the common model: {0}")]
+    Identity(SunSpecReadError),
+    #[error("Can't read the registers of model {0}: {1}")]
+    Read(u16, SunSpecReadError),
+    #[error("Catalog for model {0} is incomplete: {1}")]
+    Catalog(u16, SunSpecGroupError),
+    #[error("Model {0} appears again at {1}, only the first is kept")]
+    Duplicate(u16, Address),
+    #[error("Stopped after {0} models without finding the end marker")]
+    MaxModels(usize),
+    #[error("Model at {0} extends past the address limit")]
+    MaxAddress(Address),
+}
+
+/// The outcome of walking a device's model chain.  Discovery keeps whatever it found before an
+/// error; `complete` says whether it got as far as the end marker.
+#[derive(Debug, Default)]
+pub struct DiscoveryReport {
+    pub models: HashMap<u16, ModelData>,
+    /// problems with individual models, and the reason the walk stopped early, if it did
+    pub errors: Vec<DiscoveryError>,
+    /// whether the end marker was reached
+    pub complete: bool,
+    /// the device's identity, if its common model could be read
+    pub identity: Option<DeviceIdentity>,
+}
+
+impl SunSpecConnection {
+    /// Walk the device's model chain like populate_models, but tolerate failures: models found
+    /// before an error are kept, and every problem is reported rather than aborting the walk.
+    /// The models, catalog and device identity found are stored on the connection, as
+    /// populate_models does, unless no model was found at all.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - the SunSpecData library to load model definitions from
+    /// * `limits` - when to stop if the end marker doesn't turn up
+    pub async fn discover_models(
+        &self,
+        data: &SunSpecData,
+        limits: &DiscoveryLimits,
+    ) -> DiscoveryReport {
+        self.discover_models_at(data, SUNSPEC_FIRST_MODEL_ADDRESS, limits)
+            .await
+    }
+
+    /// discover_models, for a device whose model chain doesn't start at the preferred base
+    /// register.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - the SunSpecData library to load model definitions from
+    /// * `start` - the address of the first model's ID register, two after the `SunS` marker
+    /// * `limits` - when to stop if the end marker doesn't turn up
+    pub async fn discover_models_at(
+        &self,
+        data: &SunSpecData,
+        start: Address,
+        limits: &DiscoveryLimits,
+    ) -> DiscoveryReport {
+        let mut report = DiscoveryReport::default();
+        let mut catalog: HashMap<String, PointNode> = HashMap::new();
+        let mut address = start;
+        // every model walked counts towards the limit, repeated ones included
+        let mut walked: usize = 0;
+        loop {
+            if walked >= limits.max_models {
+                report
+                    .errors
+                    .push(DiscoveryError::MaxModels(limits.max_models));
+                break;
+            }
+            let id = match self.get_u16_no_check(address).await {
+                Ok(id) => id,
+                Err(e) => {
+                    report.errors.push(DiscoveryError::IdRead(address, e));
+                    break;
+                }
+            };
+            if id == 0 {
+                report.errors.push(DiscoveryError::InvalidId(address));
+                break;
+            }
+            let length = match self.get_u16(address + 1).await {
+                Ok(length) => length,
+                Err(e) => {
+                    report
+                        .errors
+                        .push(DiscoveryError::LengthRead(address, id, e));
+                    break;
+                }
+            };
+            if id == SUNSPEC_END_MODEL_ID {
+                report.complete = true;
+                break;
+            }
+            let next = match length
+                .checked_add(ADDR_OFFSET)
+                .and_then(|len| address.checked_add(len))
+            {
+                Some(next) if next - 1 <= limits.max_address => next,
+                _ => {
+                    report.errors.push(DiscoveryError::MaxAddress(address));
+                    break;
+                }
+            };
+            info!("found model with id {id}, and length {length}");
+            walked += 1;
+            if report.models.contains_key(&id) {
+                report.errors.push(DiscoveryError::Duplicate(id, address));
+                address = next;
+                continue;
+            }
+            if id == COMMON_MODEL_ID && report.identity.is_none() {
+                let identity = match self.read_identity(address).await {
+                    Ok(identity) => Some(identity),
+                    Err(e) => {
+                        report.errors.push(DiscoveryError::Identity(e));
+                        None
+                    }
+                };
+                report.identity = self.complete_identity(identity, limits).await;
+            }
+            // vendor models are looked up by the manufacturer named in the common model
+            let manufacturer = report.identity.as_ref().map(|i| i.manufacturer.clone());
+            let md = match ModelData::new(data, id, length, address, manufacturer).await {
+                Ok(md) => {
+                    match self.get_raw(md.address + 2, md.len).await {
+                        Ok(mut registers) => {
+                            if let Err(e) = process_group(
+                                &mut registers,
+                                &md.model.group,
+                                None,
+                                &mut md.address.clone(),
+                                &mut catalog,
+                            )
+                            .await
+                            {
+                                report.errors.push(DiscoveryError::Catalog(id, e));
+                            }
+                        }
+                        Err(e) => report.errors.push(DiscoveryError::Read(id, e)),
+                    }
+                    md
+                }
+                Err(e) => {
+                    // keep the model, so its registers can still be read raw
+                    warn!("Couldn't create ModelData, keeping model {id} undefined: {e}");
+                    ModelData::undefined(id, length, address)
+                }
+            };
+            report.models.insert(id, md);
+            address = next;
+        }
+        // a device with models but no common model may still say who it is
+        if report.identity.is_none()
+            && !report.models.is_empty()
+            && !report.models.contains_key(&COMMON_MODEL_ID)
+        {
+            report.identity = self.complete_identity(None, limits).await;
+        }
+        // a walk that found nothing, e.g. because the device didn't answer, leaves what an
+        // earlier one found in place
+        if !report.models.is_empty() {
+            *self.models.write().unwrap() = report.models.clone();
+            *self.catalog.write().unwrap() = catalog;
+            *self.identity.write().unwrap() = report.identity.clone();
+        }
+        report
+    }
+
+    /// Fall back on Read Device Identification for an identity the common model couldn't
+    /// provide, or filled in only partially.
+    async fn complete_identity(
+        &self,
+        identity: Option<DeviceIdentity>,
+        limits: &DiscoveryLimits,
+    ) -> Option<DeviceIdentity> {
+        if identity.as_ref().is_some_and(|i| !i.is_partial()) {
+            return identity;
+        }
+        let mei = match self
+            .read_device_identification(limits.identification_timeout)
+            .await
+        {
+            Ok(mei) => mei,
+            Err(e) => {
+                debug!("[{}] no device identification either: {e}", self.addr);
+                return identity;
+            }
+        };
+        match identity {
+            Some(mut identity) => {
+                identity.fill_from(&mei);
+                Some(identity)
+            }
+            None => Some(mei.into()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_discovery_limits() {
+    use crate::modbus_test_harness::ModbusTestHarness;
+
+    // the harness has model 1 (66 registers) at 40002 and the end marker at 40070
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: vec![0],
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    let data = SunSpecData::default();
+    let report = conn
+        .discover_models(&data, &DiscoveryLimits::default())
+        .await;
+    assert!(report.complete);
+    assert!(report.models.contains_key(&1));
+
+    let limits = DiscoveryLimits::builder().max_models(0).build();
+    let report = conn.discover_models(&data, &limits).await;
+    assert!(!report.complete);
+    assert!(report.models.is_empty());
+    assert_eq!(report.errors, vec![DiscoveryError::MaxModels(0)]);
+    // finding nothing doesn't forget the models found before
+    assert_eq!(conn.models().len(), 1);
+
+    let limits = DiscoveryLimits::builder().max_address(40050).build();
+    let report = conn.discover_models(&data, &limits).await;
+    assert_eq!(report.errors, vec![DiscoveryError::MaxAddress(40002)]);
+    assert!(conn.populate_models(&data).await.is_ok());
+    assert_eq!(conn.models().len(), 1);
+}
+
+#[tokio::test]
+async fn test_identity_fallback() {
+    use crate::modbus_test_harness::ModbusTestHarness;
+
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: vec![0],
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    // a common model that couldn't be read at all
+    let identity = conn
+        .complete_identity(None, &DiscoveryLimits::default())
+        .await
+        .unwrap();
+    assert_eq!(identity.manufacturer, "TestVendor");
+    assert_eq!(identity.model, "TH-1");
+    assert_eq!(identity.version.as_deref(), Some("1.0"));
+    assert!(identity.serial_number.is_empty());
+
+    // a partial one keeps what it has
+    let partial = DeviceIdentity {
+        model: "Inverter".to_string(),
+        serial_number: "42".to_string(),
+        ..Default::default()
+    };
+    let identity = conn
+        .complete_identity(Some(partial), &DiscoveryLimits::default())
+        .await
+        .unwrap();
+    assert_eq!(identity.manufacturer, "TestVendor");
+    assert_eq!(identity.model, "Inverter");
+    assert_eq!(identity.serial_number, "42");
+}
+
+#[tokio::test]
+async fn test_partial_discovery() {
+    use crate::modbus_test_harness::ModbusTestHarness;
+    use crate::sunspec_connection::Word;
+    use std::collections::HashSet;
+
+    // after the harness's common model: model 103 twice, then the end marker
+    let registers: HashMap<Address, Word> = HashMap::from([
+        (40070, 103),
+        (40071, 50),
+        (40122, 103),
+        (40123, 50),
+        (40174, SUNSPEC_END_MODEL_ID),
+        (40175, 0),
+    ]);
+    let data = SunSpecData::default();
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: vec![0],
+            registers: registers.clone(),
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    let report = conn
+        .discover_models(&data, &DiscoveryLimits::default())
+        .await;
+    assert!(report.complete);
+    assert_eq!(report.models.len(), 2);
+    assert_eq!(report.models[&103].address, 40070);
+    assert_eq!(report.errors, vec![DiscoveryError::Duplicate(103, 40122)]);
+
+    // the repeat counts towards the limit
+    let limits = DiscoveryLimits::builder().max_models(2).build();
+    let report = conn.discover_models(&data, &limits).await;
+    assert!(!report.complete);
+    assert_eq!(report.errors.last(), Some(&DiscoveryError::MaxModels(2)));
+
+    // a read failing partway through the chain keeps the models before it
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: vec![0],
+            registers,
+            failing: HashSet::from([40122]),
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    let report = conn
+        .discover_models(&data, &DiscoveryLimits::default())
+        .await;
+    assert!(!report.complete);
+    assert!(matches!(
+        report.errors[..],
+        [DiscoveryError::IdRead(40122, _)]
+    ));
+    assert_eq!(report.models.len(), 2);
+    assert!(conn.models().contains_key(&103));
+    assert!(conn.catalog_entry(".inverter_three_phase.A").is_some());
+    assert!(conn.identity().is_some());
+
+    // and a walk that fails straight away leaves them in place
+    let report = conn
+        .discover_models_at(&data, 40122, &DiscoveryLimits::default())
+        .await;
+    assert!(report.models.is_empty());
+    assert_eq!(conn.models().len(), 2);
+    assert!(conn.catalog_entry(".inverter_three_phase.A").is_some());
+}
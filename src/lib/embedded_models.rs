@@ -0,0 +1,26 @@
+//! The bundled model library, compiled in by the `embedded-models` feature.
+
+include!(concat!(env!("OUT_DIR"), "/embedded_models.rs"));
+
+/// Look up a bundled model file by its path relative to `models/`, e.g. `model_1.json` or
+/// `generac/smdx_64200.xml`.
+pub(crate) fn get(name: &str) -> Option<&'static [u8]> {
+    EMBEDDED_MODELS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, data)| *data)
+}
+
+/// Returns the names of all bundled model files, relative to `models/`.
+pub(crate) fn names() -> impl Iterator<Item = &'static str> {
+    EMBEDDED_MODELS.iter().map(|(n, _)| *n)
+}
+
+#[test]
+fn test_embedded_models() {
+    assert!(get("model_1.json").is_some());
+    assert!(get("smdx_00001.xml").is_some());
+    assert!(get("generac/smdx_64200.xml").is_some());
+    assert!(get("pika/smdx_64200.xml").is_some());
+    assert!(get("manifest.xml").is_none());
+}
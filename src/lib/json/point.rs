@@ -281,7 +281,9 @@ impl Default for PointStatic {
     }
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
 pub enum PointType {
     #[serde(rename = "int16")]
     Int16,
@@ -321,6 +323,7 @@ pub enum PointType {
     String,
     #[serde(rename = "sf")]
     Sf,
+    #[default]
     #[serde(rename = "pad")]
     Pad,
     #[serde(rename = "ipaddr")]
@@ -1,10 +1,24 @@
 #[macro_use]
 extern crate tracing;
 extern crate thiserror;
+pub mod catalog_query;
+pub mod device_identity;
+pub mod discovery;
+#[cfg(feature = "embedded-models")]
+mod embedded_models;
 pub mod json;
 pub mod metrics;
 pub mod modbus_test_harness;
+pub mod model_browse;
 pub mod model_data;
+pub mod model_import;
+pub mod model_manifest;
+pub mod model_tree;
+pub mod model_validation;
+pub mod poller;
+pub mod scanner;
+pub mod subnet;
 pub mod sunspec_connection;
 pub mod sunspec_data;
 pub mod sunspec_models;
+pub mod typed_models;
@@ -1,8 +1,11 @@
-use crate::sunspec_connection::{SunSpecConn, Word};
+use crate::sunspec_connection::{
+    SunSpecConn, Word, ERROR_ILLEGAL_DATA_VALUE, ERROR_INVALID_RESPONSE_HEADER,
+};
 use async_trait::async_trait;
-use bitvec::macros::internal::funty::Fundamental;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
-use std::io::Error;
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use tokio_modbus::client::{Client, Reader, Writer};
 use tokio_modbus::prelude::SlaveContext;
@@ -14,8 +17,23 @@ pub enum TestError {
     Misc(String),
 }
 
+#[derive(Default)]
 pub struct ModbusTestHarness {
     pub buf: Vec<u16>,
+    /// registers holding a value of their own, read in place of the canned responses; writes
+    /// land here too
+    pub registers: HashMap<Address, Word>,
+    /// registers that can't be read; a read touching any of them fails with an exception
+    pub failing: HashSet<Address>,
+    /// the slave id the last request was addressed to; shared, so a test can keep a handle
+    pub slave: Arc<Mutex<Option<u8>>>,
+    /// slave ids that answer only after the request has timed out
+    pub silent: HashSet<u8>,
+    /// whether a late reply is waiting on the simulated socket; until it's reopened, every read
+    /// gets the reply meant for the request before it, and fails as tokio-modbus does
+    pub late_reply: bool,
+    /// whether Read Device Identification is answered only after the request has timed out
+    pub slow_identification: bool,
 }
 type Coil = bool;
 
@@ -25,8 +43,9 @@ impl Writer for ModbusTestHarness {
         todo!()
     }
 
-    async fn write_single_register(&mut self, _: Address, _: Word) -> Result<(), Error> {
-        todo!()
+    async fn write_single_register(&mut self, addr: Address, word: Word) -> Result<(), Error> {
+        self.registers.insert(addr, word);
+        Ok(())
     }
 
     async fn write_multiple_coils(
@@ -39,10 +58,13 @@ impl Writer for ModbusTestHarness {
 
     async fn write_multiple_registers(
         &mut self,
-        _addr: Address,
-        _data: &[Word],
+        addr: Address,
+        data: &[Word],
     ) -> Result<(), Error> {
-        todo!()
+        for (i, word) in data.iter().enumerate() {
+            self.registers.insert(addr + i as Address, *word);
+        }
+        Ok(())
     }
 
     async fn masked_write_register(&mut self, _: Address, _: Word, _: Word) -> Result<(), Error> {
@@ -50,17 +72,49 @@ impl Writer for ModbusTestHarness {
     }
 }
 
-impl SunSpecConn for ModbusTestHarness {}
+impl SunSpecConn for ModbusTestHarness {
+    fn reopen(&self) -> Option<Box<dyn SunSpecConn>> {
+        Some(Box::new(ModbusTestHarness {
+            buf: self.buf.clone(),
+            registers: self.registers.clone(),
+            failing: self.failing.clone(),
+            slave: self.slave.clone(),
+            silent: self.silent.clone(),
+            late_reply: false,
+            slow_identification: self.slow_identification,
+        }))
+    }
+}
 #[async_trait]
 impl Client for ModbusTestHarness {
-    async fn call(&mut self, _request: Request<'_>) -> Result<Response, Error> {
-        todo!()
+    async fn call(&mut self, request: Request<'_>) -> Result<Response, Error> {
+        self.answer(self.slow_identification).await?;
+        // read device identification, split over two responses: the vendor name, then the rest
+        let response: Vec<u8> = match request {
+            Request::Custom(0x2B, data) => match data.as_ref() {
+                [0x0E, 0x01, 0x00] => [
+                    &[0x0E, 0x01, 0x01, 0xFF, 0x01, 0x01, 0x00, 0x0A][..],
+                    b"TestVendor",
+                ]
+                .concat(),
+                [0x0E, 0x01, 0x01] => [
+                    &[0x0E, 0x01, 0x01, 0x00, 0x00, 0x02, 0x01, 0x04][..],
+                    b"TH-1",
+                    &[0x02, 0x03],
+                    b"1.0",
+                ]
+                .concat(),
+                _ => return Err(Error::new(ErrorKind::InvalidInput, "unsupported object id")),
+            },
+            _ => return Err(Error::new(ErrorKind::Unsupported, "unsupported request")),
+        };
+        Ok(Response::Custom(0x2B, response.into()))
     }
 }
 
 impl SlaveContext for ModbusTestHarness {
-    fn set_slave(&mut self, _slave: Slave) {
-        todo!()
+    fn set_slave(&mut self, slave: Slave) {
+        *self.slave.lock().unwrap() = Some(slave.0);
     }
 }
 
@@ -83,40 +137,15 @@ impl Reader for ModbusTestHarness {
     async fn read_holding_registers(
         &mut self,
         addr: Address,
-        _: Quantity,
+        quantity: Quantity,
     ) -> Result<Vec<Word>, Error> {
-        let address = addr.as_u16();
-        return match address {
-            40002 => {
-                let resp: Vec<u16> = vec![1_u16];
-                Ok(resp)
-            }
-            40003 => {
-                let resp: Vec<u16> = vec![66_u16];
-                Ok(resp)
-            }
-            40004 => {
-                let resp = "Test".to_string();
-                Ok(string_to_vec_word(resp))
-            }
-            40020 => {
-                let resp = "TestHarness".to_string();
-                Ok(string_to_vec_word(resp))
-            }
-            40052 => {
-                let resp = "1234567890".to_string();
-                Ok(string_to_vec_word(resp))
-            }
-            40070 => {
-                let resp: Vec<u16> = vec![65535_u16];
-                Ok(resp)
-            }
-            40071 => {
-                let resp: Vec<u16> = vec![0_u16];
-                Ok(resp)
-            }
-            _ => Ok(self.buf.clone()),
-        };
+        self.answer(false).await?;
+        if (addr..addr.saturating_add(quantity)).any(|a| self.failing.contains(&a)) {
+            return Err(Error::other(ERROR_ILLEGAL_DATA_VALUE));
+        }
+        Ok((0..quantity)
+            .map(|i| self.register(addr + i, i as usize))
+            .collect())
     }
 
     async fn read_input_registers(&mut self, _: Address, _: Quantity) -> Result<Vec<Word>, Error> {
@@ -134,6 +163,48 @@ impl Reader for ModbusTestHarness {
     }
 }
 
+impl ModbusTestHarness {
+    /// Fail the way a desynchronized socket does, or never answer a late request or one to a
+    /// silent slave, leaving its reply to come in late.
+    async fn answer(&mut self, late: bool) -> Result<(), Error> {
+        if self.late_reply {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{ERROR_INVALID_RESPONSE_HEADER} transaction id mismatch"),
+            ));
+        }
+        let slave = *self.slave.lock().unwrap();
+        if late || slave.is_some_and(|s| self.silent.contains(&s)) {
+            self.late_reply = true;
+            std::future::pending::<()>().await;
+        }
+        Ok(())
+    }
+
+    /// The value of one register, the `offset`th of a read: from `registers` if it's there, then
+    /// the common model at 40002, and otherwise from `buf`, which every other read starts at.
+    fn register(&self, addr: Address, offset: usize) -> Word {
+        if let Some(word) = self.registers.get(&addr) {
+            return *word;
+        }
+        let mut common: HashMap<Address, Word> = HashMap::new();
+        let mut put = |start: Address, words: Vec<Word>| {
+            for (i, word) in words.into_iter().enumerate() {
+                common.insert(start + i as Address, word);
+            }
+        };
+        put(40002, vec![1, 66]);
+        put(40004, string_to_vec_word("Test".to_string()));
+        put(40020, string_to_vec_word("TestHarness\0".to_string()));
+        put(40052, string_to_vec_word("1234567890".to_string()));
+        put(40070, vec![65535, 0]);
+        match common.get(&addr) {
+            Some(word) => *word,
+            None => self.buf.get(offset).copied().unwrap_or(0),
+        }
+    }
+}
+
 pub fn string_to_vec_word(input: String) -> Vec<u16> {
     debug_assert!(input.len() % 2 == 0);
     input
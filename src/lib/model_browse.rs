@@ -0,0 +1,256 @@
+use crate::json::group::GroupCount;
+use crate::json::point::PointType;
+use crate::model_data::ModelData;
+use crate::sunspec_connection::{find_in_scope, point_len, PointNode, SunSpecConnection};
+use crate::sunspec_models::{Access, ModelGroup, Symbol, ValueType};
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio_modbus::Address;
+
+/// The definition of one point as it is laid out on a device, for building a UI or
+/// configuration without reading anything.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PointInfo {
+    /// the point's catalog path, e.g. `.DERVoltVar.Crv[2].Pt[1].V`
+    pub path: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: PointType,
+    /// length in registers
+    pub size: u16,
+    /// absolute register address
+    pub address: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub units: Option<String>,
+    /// the scale factor: the name of the point holding it, or a fixed exponent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sf: Option<String>,
+    pub access: Access,
+    pub mandatory: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<Symbol>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// One instance of a group as it is laid out on a device: where it starts, and the instances of
+/// the groups nested in it.
+pub(crate) struct GroupLayout<'a> {
+    pub group: &'a ModelGroup,
+    /// the 1-based instance number, for a group with more than one instance
+    pub index: Option<u16>,
+    /// the instance's catalog path, e.g. `.DERVoltVar.Crv[2]`
+    pub path: String,
+    /// the address of the instance's first register
+    pub start: Address,
+    pub groups: Vec<GroupLayout<'a>>,
+}
+
+impl ModelData {
+    /// List every point of this model in group order, with its absolute address.
+    ///
+    /// Groups repeated a fixed number of times, and groups that fill the rest of the model, are
+    /// laid out from the definition and the model's length.  A group whose count is given by a
+    /// point takes that count from `catalog`; if the catalog doesn't have it, the position of
+    /// everything after it depends on the device, so the listing stops there.  Pass an empty
+    /// catalog to list only what the definition alone determines.
+    ///
+    /// # Arguments
+    ///
+    /// * `catalog` - the point catalog of the connection this model was populated from
+    pub fn points(&self, catalog: &HashMap<String, PointNode>) -> Vec<PointInfo> {
+        let mut points: Vec<PointInfo> = vec![];
+        if let Some(root) = self.layout(catalog) {
+            list_points(&root, &mut points);
+        }
+        points
+    }
+
+    /// Lay out the instances of this model's groups, as `points` lists them.  Returns None if
+    /// the root group couldn't be laid out.
+    pub(crate) fn layout(&self, catalog: &HashMap<String, PointNode>) -> Option<GroupLayout<'_>> {
+        let mut address = self.address;
+        // the model's registers, counting ID and L
+        let end = self.address + self.len + 2;
+        let mut root: Vec<GroupLayout> = vec![];
+        layout_group(
+            &self.model.group,
+            None,
+            &mut address,
+            end,
+            catalog,
+            &mut root,
+        );
+        root.into_iter().next()
+    }
+}
+
+impl SunSpecConnection {
+    /// List every point of a populated model in group order, with its absolute address.  See
+    /// ModelData::points.
+    pub fn model_points(&self, model_id: u16) -> Option<Vec<PointInfo>> {
+        Some(self.model(model_id)?.points(&self.catalog.read().unwrap()))
+    }
+}
+
+/// Lays out every instance of `group`, returning false if the layout had to stop because an
+/// instance count couldn't be determined.
+fn layout_group<'a>(
+    group: &'a ModelGroup,
+    prefix: Option<&str>,
+    address: &mut Address,
+    end: Address,
+    catalog: &HashMap<String, PointNode>,
+    instances: &mut Vec<GroupLayout<'a>>,
+) -> bool {
+    let group_path = format!("{}.{}", prefix.unwrap_or_default(), group.name);
+    let entries: u16 = match &group.count {
+        GroupCount::String(name) => {
+            let count = find_in_scope(catalog, &group_path, name)
+                .and_then(|path| catalog.get(&path))
+                .map(|pn| &pn.value);
+            match count {
+                Some(ValueType::Integer(n)) if *n >= 0 => *n as u16,
+                _ => {
+                    debug!("count {name} for {group_path} is unknown, listing stops here");
+                    return false;
+                }
+            }
+        }
+        GroupCount::Integer(0) => match group.instance_len() {
+            Some(len) if len > 0 => end.saturating_sub(*address) / len,
+            _ => return false,
+        },
+        GroupCount::Integer(n) => *n as u16,
+    };
+    for i in 0..entries {
+        // instances are named the way the catalog names them
+        let (path, index) = if entries > 1 {
+            (format!("{group_path}[{}]", i + 1), Some(i + 1))
+        } else {
+            (group_path.clone(), None)
+        };
+        let start = *address;
+        for p in group.points.iter() {
+            *address = (*address).max(start + p.offset + point_len(p).unwrap_or(0));
+        }
+        let mut instance = GroupLayout {
+            group,
+            index,
+            path,
+            start,
+            groups: vec![],
+        };
+        for g in group.groups.iter() {
+            if !layout_group(
+                g,
+                Some(&instance.path),
+                address,
+                end,
+                catalog,
+                &mut instance.groups,
+            ) {
+                instances.push(instance);
+                return false;
+            }
+        }
+        instances.push(instance);
+    }
+    true
+}
+
+fn list_points(instance: &GroupLayout, points: &mut Vec<PointInfo>) {
+    for p in instance.group.points.iter() {
+        let literal = p.literal.clone().unwrap_or_default();
+        points.push(PointInfo {
+            path: format!("{}.{}", instance.path, p.id),
+            name: p.id.clone(),
+            type_: p.r#type,
+            size: point_len(p).unwrap_or(0),
+            address: instance.start + p.offset,
+            units: p.units.clone(),
+            sf: p.scale_factor.clone(),
+            access: p.access.clone().unwrap_or(Access::ReadOnly),
+            mandatory: p.mandatory.unwrap_or(false),
+            symbols: p.symbol.clone().unwrap_or_default(),
+            label: literal.label,
+            description: literal.description,
+        });
+    }
+    for g in instance.groups.iter() {
+        list_points(g, points);
+    }
+}
+
+#[tokio::test]
+async fn test_model_points() {
+    use crate::sunspec_connection::process_group;
+    use crate::sunspec_data::SunSpecData;
+
+    let model = SunSpecData::default().get_model(705, None).unwrap();
+    let md = ModelData {
+        id: 705,
+        len: 62,
+        address: 40002,
+        model: model.clone(),
+        scale_factors: Default::default(),
+    };
+    // without a catalog, the curves' count (NCrv) is unknown, so only the root points are listed
+    let fixed = md.points(&HashMap::new());
+    assert_eq!(fixed[0].path, ".DERVoltVar.ID");
+    assert_eq!(fixed[2].address, 40004);
+    assert!(fixed.iter().all(|p| !p.path.contains(".Crv")));
+    let ena = fixed.iter().find(|p| p.name == "Ena").unwrap();
+    assert_eq!(ena.access, Access::ReadWrite);
+    assert_eq!(ena.symbols.len(), 2);
+
+    // registers after ID and L: NPt = 2, NCrv = 2
+    let mut data: Vec<u16> = vec![0; 64];
+    data[3] = 2;
+    data[4] = 2;
+    let mut catalog: HashMap<String, PointNode> = HashMap::new();
+    process_group(&mut data, &model.group, None, &mut 40002, &mut catalog)
+        .await
+        .unwrap();
+    let points = md.points(&catalog);
+    let last = points.last().unwrap();
+    assert!(
+        last.path.starts_with(".DERVoltVar.Crv[2]."),
+        "{}",
+        last.path
+    );
+    assert_eq!(
+        points
+            .iter()
+            .filter(|p| p.path.starts_with(".DERVoltVar.Crv[2].Pt[2]."))
+            .count(),
+        2
+    );
+    // every cataloged point is listed at the address the catalog has for it
+    for p in points.iter() {
+        if let Some(pn) = catalog.get(&p.path) {
+            assert_eq!(p.address, pn.address, "{}", p.path);
+        }
+    }
+    assert!(catalog.keys().all(|k| points.iter().any(|p| p.path == *k)));
+
+    // a group that fills the model repeats as often as the model's length allows
+    let fd = std::fs::File::open("models/generac/smdx_64212.xml").unwrap();
+    let model: crate::sunspec_models::SunSpecModels = serde_xml_rs::from_reader(fd).unwrap();
+    let md = ModelData {
+        id: 64212,
+        len: 64,
+        address: 40100,
+        model: std::sync::Arc::new(model),
+        scale_factors: Default::default(),
+    };
+    let points = md.points(&HashMap::new());
+    let soc = points
+        .iter()
+        .find(|p| p.path == ".DCB105_warranty.DCB105_module[3].SOC")
+        .unwrap();
+    assert_eq!(soc.address, 40100 + 2 + 16 + 2 * 16 + 9);
+    assert!(!points.iter().any(|p| p.path.contains("module[4]")));
+}
@@ -1,27 +1,31 @@
-use crate::sunspec_connection::SunSpecConnection;
+use crate::sunspec_connection::{SunSpecConnection, ADDR_OFFSET};
 use crate::sunspec_data::{ResolvedModel, SunSpecData};
 use crate::sunspec_models::{
-    GroupIdentifier, LiteralType, OptionalGroupIdentifier, PointIdentifier, SunSpecModels,
-    ValueType,
+    GroupIdentifier, Model, ModelGroup, ModelSource, PointIdentifier, SunSpecModels, ValueType,
 };
 use std::collections::HashMap;
 use std::string::ToString;
+use std::sync::{Arc, RwLock};
 use thiserror::Error;
 use tokio_modbus::Address;
 
+/// ModelData ties a model definition to the place it was found on a device.  It is cheap to
+/// clone: clones share the definition and the scale factor cache.
 #[derive(Default, Debug, Clone)]
 pub struct ModelData {
     pub id: u16,
     pub len: u16,
     pub address: Address,
-    pub model: SunSpecModels,
-    pub scale_factors: HashMap<String, i16>,
+    pub model: Arc<SunSpecModels>,
+    pub scale_factors: Arc<RwLock<HashMap<String, i16>>>,
 }
 
 #[derive(Error, Debug, Default)]
 pub enum SunSpecModelDataError {
     #[error("While calculating repeating block size, division had a remainder.")]
     Remainder,
+    #[error("The repeating group's length depends on the device, so it can't be counted.")]
+    VariableLength,
     #[error("Default error")]
     #[default]
     Error,
@@ -37,7 +41,7 @@ impl ModelData {
     /// * `len` - The length of this model (returned when querying the model)
     /// * `address` - Where this particular model exists in the address range
     pub async fn new(
-        data: SunSpecData,
+        data: &SunSpecData,
         id: u16,
         len: u16,
         address: Address,
@@ -52,41 +56,64 @@ impl ModelData {
             len,
             address,
             model: model.unwrap(),
-            scale_factors: HashMap::default(),
+            scale_factors: Arc::default(),
         })
     }
-    /// Returns the number of blocks for this model
-    pub fn get_block_count(self) -> Result<u16, SunSpecModelDataError> {
-        let model = self.model.model.clone();
-        let first_block_type = model.block[0]
-            .r#type
-            .clone()
-            .unwrap_or(String::from("none"));
-        let model_len = self.len; // the length of the actual model
+    /// Create a ModelData for a model the device exposes but no definition describes, so that
+    /// it can still be listed and its registers read with SunSpecConnection::read_model_raw.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The model id number read from the device
+    /// * `len` - The length of this model (returned when querying the model)
+    /// * `address` - Where this particular model exists in the address range
+    pub fn undefined(id: u16, len: u16, address: Address) -> Self {
+        let model = SunSpecModels {
+            model: Model {
+                id,
+                len,
+                ..Default::default()
+            },
+            source: ModelSource::Undefined,
+            ..Default::default()
+        };
+        ModelData {
+            id,
+            len,
+            address,
+            model: Arc::new(model),
+            scale_factors: Arc::default(),
+        }
+    }
 
-        if first_block_type == "repeating" {
-            // if the first block is a 'repeating' block, per the standard, there are no fixed blocks.
-            let fixed_block_len = 0_u16;
-            let repeat_block_len = model.block[0].len; // the length of the repeating block
-            if (model_len - fixed_block_len) % repeat_block_len != 0 {
-                return Err(SunSpecModelDataError::Remainder);
-            }
-            let num_blocks = (model_len - fixed_block_len) / repeat_block_len;
-            Ok(num_blocks)
-        } else if self.model.model.block.len() > 1 {
-            // the length of the array of read-in blocks
-            let fixed_block_len = model.block[0].len; // the length of the fixed block
-            let repeat_block_len = model.block[1].len; // the length of the repeating block
-            if (model_len - fixed_block_len) % repeat_block_len != 0 {
-                return Err(SunSpecModelDataError::Remainder);
-            }
-            let num_blocks = (model_len - fixed_block_len) / repeat_block_len;
-            Ok(num_blocks)
-        } else {
-            // this should always be 1
-            assert_eq!(model.block.len(), 1);
-            Ok(model.block.len() as u16)
+    /// Whether a definition was found for this model.
+    pub fn is_defined(&self) -> bool {
+        self.model.source != ModelSource::Undefined
+    }
+
+    /// Returns the number of blocks for this model: how many instances of its repeating group
+    /// fit in the model's length, or 1 if it has none.
+    pub fn get_block_count(&self) -> Result<u16, SunSpecModelDataError> {
+        let (fixed_len, group) = match self.repeating_group() {
+            Some(g) => g,
+            None => return Ok(1),
+        };
+        let repeat_len = match group.instance_len() {
+            Some(len) if len > 0 => len,
+            _ => return Err(SunSpecModelDataError::VariableLength),
+        };
+        // fixed_len counts ID and L, which the model's length doesn't
+        let model_len = (self.len + ADDR_OFFSET).saturating_sub(fixed_len);
+        if model_len % repeat_len != 0 {
+            return Err(SunSpecModelDataError::Remainder);
         }
+        Ok(model_len / repeat_len)
+    }
+
+    /// Returns the offset of the repeating group from the model's ID register, and the group's
+    /// definition, if this model has a repeating group.
+    pub(crate) fn repeating_group(&self) -> Option<(u16, &ModelGroup)> {
+        self.model.group.repeating()
     }
 
     /// For a given model point, retrieve its scale factor and store it for later re-use.
@@ -95,23 +122,34 @@ impl ModelData {
     ///
     /// * `name` - The name of the point inside our model to query
     /// * `conn` - The SunSpecConnection we have open already (so that we can query the proper connection)
+    /// * `block` - The repeat instance of the scaled point, for scale factors in the repeating block
     pub async fn get_scale_factor(
-        &mut self,
+        &self,
         name: &str,
-        conn: SunSpecConnection,
+        conn: &SunSpecConnection,
         block: Option<GroupIdentifier>,
         addr: Option<u16>,
     ) -> Option<i16> {
-        if let Some(value) = self.scale_factors.get(name) {
-            return Some(*value);
-        } else {
-            if let Ok(point) = conn
-                .clone()
-                .get_point(self.clone(), PointIdentifier::Point(name.parse().unwrap()))
-                .await
+        // some definitions give the scale factor itself rather than the point holding it
+        if let Ok(sf) = name.parse::<i16>() {
+            return Some(sf);
+        }
+        let identifier = match (block, self.repeating_group()) {
+            (Some(GroupIdentifier::Integer(n)), Some((_, g)))
+                if g.points.iter().any(|p| p.id == name) =>
             {
+                PointIdentifier::Repeating(n, name.to_string())
+            }
+            _ => PointIdentifier::Point(name.to_string()),
+        };
+        let key = identifier.to_string();
+        let cached = self.scale_factors.read().unwrap().get(&key).copied();
+        if let Some(value) = cached {
+            return Some(value);
+        } else {
+            if let Ok(point) = conn.get_point(self, identifier).await {
                 if let Some(ValueType::Integer(val)) = point.value {
-                    self.scale_factors.insert(name.to_string(), val as i16);
+                    self.scale_factors.write().unwrap().insert(key, val as i16);
                     return Some(val as i16);
                 };
             } else {
@@ -122,20 +160,41 @@ impl ModelData {
     }
 
     /// Return a model object that contains descriptive data from the modelfile to explain what this model is
-    pub async fn get_resolved_model(self) -> ResolvedModel {
+    pub async fn get_resolved_model(&self) -> ResolvedModel {
         let mut resolved_model: ResolvedModel = ResolvedModel::default();
 
-        let strings = &self.model.clone().strings[0];
-        for l in strings.literals.iter() {
-            if let LiteralType::Model(model_literal) = l {
-                resolved_model.notes = model_literal.notes.clone();
-                resolved_model.description = model_literal.description.clone();
-                resolved_model.label = model_literal.label.clone();
-            }
-        }
+        let text = self.model.model_text(None);
+        resolved_model.notes = text.notes;
+        resolved_model.description = text.description;
+        resolved_model.label = text.label;
 
-        resolved_model.model = self.model.model;
+        resolved_model.model = self.model.model.clone();
         debug!("resolved model is {:#?}", resolved_model);
         resolved_model
     }
 }
+
+#[tokio::test]
+async fn test_resolved_model_without_strings() {
+    // JSON definitions carry no SMDX strings
+    let model = SunSpecData::default().get_model(705, None).unwrap();
+    let md = ModelData {
+        id: 705,
+        len: model.model.len,
+        address: 40000,
+        model,
+        scale_factors: Arc::default(),
+    };
+    let resolved = md.get_resolved_model().await;
+    assert_eq!(resolved.label.as_deref(), Some("DER Volt-Var"));
+}
+
+#[test]
+fn test_undefined_model() {
+    let md = ModelData::undefined(64999, 10, 40100);
+    assert!(!md.is_defined());
+    assert_eq!(md.model.model.id, 64999);
+    assert_eq!(md.get_block_count().unwrap(), 1);
+    assert!(md.tree(&HashMap::new()).is_none());
+    assert!(md.points(&HashMap::new()).is_empty());
+}
@@ -0,0 +1,416 @@
+//! Importing model definitions from a local checkout of the upstream SunSpec models repository,
+//! which keeps JSON definitions in `json/` and SMDX definitions in `smdx/`.
+use crate::json::group::Group;
+use crate::json::misc::JSONModel;
+use crate::json::point::Point;
+use crate::model_manifest::{update_manifest, ManifestError};
+use crate::model_validation::file_model_id;
+use crate::sunspec_models::SmdxFile;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Files besides the model definitions that are kept in step with upstream.  The manifest isn't
+/// one of them: it describes the library, so it is updated after an import instead.
+const SUPPORT_FILES: &[&str] = &["schema.json", "smdx.xsd"];
+const MANIFEST: &str = "manifest.xml";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ImportError {
+    #[error("Can't access {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("{0} has neither a json nor an smdx directory")]
+    NotACheckout(PathBuf),
+    #[error("Can't update the manifest: {0}")]
+    Manifest(ManifestError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    /// the library doesn't have this file yet
+    Added,
+    /// the library's copy differs from upstream
+    Changed,
+}
+
+/// A point whose definition differs between the library and upstream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointChange {
+    pub path: String,
+    /// one entry per changed attribute, e.g. `type: uint16 -> uint32`
+    pub changes: Vec<String>,
+}
+
+/// An upstream file that would be imported.  Point paths use the `.group.subgroup.Point` form of
+/// the point catalog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiff {
+    /// path within the library, e.g. `model_1.json`, or `generac/smdx_64200.xml` for an
+    /// upstream file the library keeps in a vendor subdirectory
+    pub name: String,
+    pub source: PathBuf,
+    pub status: FileStatus,
+    /// the model id, or None for support files such as smdx.xsd
+    pub id: Option<u16>,
+    /// for an added definition, the library's definition of the same model in the other format,
+    /// which the points were compared with, e.g. `smdx_00123.xml` for `model_123.json`
+    pub compared_with: Option<String>,
+    pub added_points: Vec<String>,
+    pub removed_points: Vec<String>,
+    pub changed_points: Vec<PointChange>,
+}
+
+/// The difference between the model library and an upstream checkout.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    /// new and changed files, sorted by name
+    pub files: Vec<FileDiff>,
+    /// number of upstream files identical to the library's copy
+    pub unchanged: usize,
+    /// library models that upstream doesn't have in either format; these are left alone
+    pub local_only: Vec<String>,
+    /// upstream files that couldn't be parsed, and won't be imported
+    pub errors: Vec<String>,
+}
+
+/// Parse a JSON or SMDX definition into the JSON model form, so both can be compared point by
+/// point.
+fn parse_model(name: &str, data: &[u8]) -> anyhow::Result<JSONModel> {
+    if name.ends_with(".json") {
+        Ok(serde_json::from_slice(data)?)
+    } else {
+        let smdx: SmdxFile = match serde_xml_rs::from_reader(data) {
+            Ok(m) => m,
+            Err(e) => anyhow::bail!("Couldn't deserialize xml: {e}"),
+        };
+        match JSONModel::try_from(&smdx) {
+            Ok(m) => Ok(m),
+            Err(e) => anyhow::bail!("Couldn't convert model {}: {e}", smdx.model.id),
+        }
+    }
+}
+
+fn collect_points<'a>(group: &'a Group, path: &str, points: &mut BTreeMap<String, &'a Point>) {
+    let path = format!("{path}.{}", group.name);
+    for p in group.points.iter() {
+        points.insert(format!("{path}.{}", p.name), p);
+    }
+    for g in group.groups.iter() {
+        collect_points(g, &path, points);
+    }
+}
+
+/// The attributes of a point that affect how it is decoded.
+fn point_attributes(p: &Point) -> Vec<(&'static str, String)> {
+    let symbols: Vec<String> = p
+        .symbols
+        .iter()
+        .map(|s| format!("{}={}", s.name, s.value))
+        .collect();
+    vec![
+        ("type", p.type_.to_string()),
+        ("size", p.size.to_string()),
+        (
+            "sf",
+            p.sf.as_ref().map(|s| s.to_string()).unwrap_or_default(),
+        ),
+        ("units", p.units.clone().unwrap_or_default()),
+        ("access", p.access.to_string()),
+        ("mandatory", p.mandatory.to_string()),
+        ("count", p.count.map(|c| c.to_string()).unwrap_or_default()),
+        ("symbols", symbols.join(",")),
+    ]
+}
+
+fn diff_points(diff: &mut FileDiff, old: &JSONModel, new: &JSONModel) {
+    let mut old_points = BTreeMap::new();
+    let mut new_points = BTreeMap::new();
+    collect_points(&old.group, "", &mut old_points);
+    collect_points(&new.group, "", &mut new_points);
+    for (path, new_point) in new_points.iter() {
+        let Some(old_point) = old_points.get(path) else {
+            diff.added_points.push(path.clone());
+            continue;
+        };
+        let changes: Vec<String> = point_attributes(old_point)
+            .into_iter()
+            .zip(point_attributes(new_point))
+            .filter(|((_, before), (_, after))| before != after)
+            .map(|((attr, before), (_, after))| format!("{attr}: {before} -> {after}"))
+            .collect();
+        if !changes.is_empty() {
+            diff.changed_points.push(PointChange {
+                path: path.clone(),
+                changes,
+            });
+        }
+    }
+    diff.removed_points = old_points
+        .keys()
+        .filter(|path| !new_points.contains_key(*path))
+        .cloned()
+        .collect();
+}
+
+/// Lists the model and support files in one upstream directory.
+fn upstream_files(dir: &Path) -> Result<Vec<(String, PathBuf)>, ImportError> {
+    let mut files = vec![];
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+    let entries = std::fs::read_dir(dir).map_err(|e| ImportError::Io(dir.to_path_buf(), e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| ImportError::Io(dir.to_path_buf(), e))?
+            .path();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        if path.is_file()
+            && (file_model_id(Path::new(&name)).is_some() || SUPPORT_FILES.contains(&&*name))
+        {
+            files.push((name, path));
+        }
+    }
+    Ok(files)
+}
+
+/// Lists the model files in the library and its vendor subdirectories, keyed by file name.
+/// Vendor subdirectories hold copies of upstream files, so one name can map to several paths,
+/// which are relative to the library and use forward slashes.
+fn library_files(
+    dir: &Path,
+    prefix: &str,
+    files: &mut BTreeMap<String, Vec<String>>,
+) -> Result<(), ImportError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| ImportError::Io(dir.to_path_buf(), e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| ImportError::Io(dir.to_path_buf(), e))?
+            .path();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        if path.is_dir() {
+            library_files(&path, &format!("{prefix}{name}/"), files)?;
+        } else if file_model_id(&path).is_some()
+            || (prefix.is_empty() && SUPPORT_FILES.contains(&&*name))
+        {
+            files
+                .entry(name.clone())
+                .or_default()
+                .push(format!("{prefix}{name}"));
+        }
+    }
+    Ok(())
+}
+
+/// Compare the model library with a checkout of the upstream SunSpec models repository.  An
+/// upstream file is compared with every copy the library has of it, including copies in vendor
+/// subdirectories; one the library doesn't have goes to the top level, and is compared with the
+/// library's definition of the same model in the other format, if there is one.
+///
+/// # Arguments
+///
+/// * `upstream` - The root of the upstream checkout, containing `json/` and `smdx/`.
+/// * `library` - The model library directory, normally `models`.
+pub fn diff_upstream(upstream: &Path, library: &Path) -> Result<ImportReport, ImportError> {
+    let (json_dir, smdx_dir) = (upstream.join("json"), upstream.join("smdx"));
+    if !json_dir.is_dir() && !smdx_dir.is_dir() {
+        return Err(ImportError::NotACheckout(upstream.to_path_buf()));
+    }
+    let mut files = upstream_files(&json_dir)?;
+    files.extend(upstream_files(&smdx_dir)?);
+    files.sort();
+    let mut local: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    library_files(library, "", &mut local)?;
+
+    let mut report = ImportReport::default();
+    for (name, source) in files.iter() {
+        let data = std::fs::read(source).map_err(|e| ImportError::Io(source.clone(), e))?;
+        let id = file_model_id(Path::new(name));
+        let new_model = match id {
+            Some(_) => match parse_model(name, &data) {
+                Ok(m) => Some(m),
+                Err(e) => {
+                    report.errors.push(format!("{}: {e}", source.display()));
+                    continue;
+                }
+            },
+            None => None,
+        };
+        let copies = match local.get(name) {
+            Some(paths) => paths.clone(),
+            None => vec![name.clone()],
+        };
+        for copy in copies {
+            let mut diff = FileDiff {
+                name: copy.clone(),
+                source: source.clone(),
+                status: FileStatus::Added,
+                id,
+                compared_with: None,
+                added_points: vec![],
+                removed_points: vec![],
+                changed_points: vec![],
+            };
+            match std::fs::read(library.join(&copy)) {
+                Ok(old) if old == data => {
+                    report.unchanged += 1;
+                    continue;
+                }
+                Ok(old) => {
+                    diff.status = FileStatus::Changed;
+                    // a library copy that doesn't parse is simply replaced
+                    if let (Some(new_model), Ok(old_model)) = (&new_model, parse_model(name, &old))
+                    {
+                        diff_points(&mut diff, &old_model, new_model);
+                    }
+                }
+                Err(_) => {
+                    let Some(new_model) = &new_model else {
+                        report.files.push(diff);
+                        continue;
+                    };
+                    // the same model, defined in the other format
+                    let other = local
+                        .iter()
+                        .filter(|(n, _)| *n != name && file_model_id(Path::new(n)) == id)
+                        .flat_map(|(_, paths)| paths.iter())
+                        .find(|p| !p.contains('/'));
+                    let old_model = other.and_then(|other| {
+                        let old = std::fs::read(library.join(other)).ok()?;
+                        parse_model(other, &old).ok()
+                    });
+                    match old_model {
+                        Some(old_model) => {
+                            diff.compared_with = other.cloned();
+                            diff_points(&mut diff, &old_model, new_model);
+                        }
+                        None => {
+                            let mut points = BTreeMap::new();
+                            collect_points(&new_model.group, "", &mut points);
+                            diff.added_points = points.into_keys().collect();
+                        }
+                    }
+                }
+            }
+            report.files.push(diff);
+        }
+    }
+
+    // a model upstream has in either format isn't local-only
+    let upstream_ids: Vec<u16> = files
+        .iter()
+        .filter_map(|(n, _)| file_model_id(Path::new(n)))
+        .collect();
+    report.local_only = local
+        .values()
+        .flatten()
+        .filter(|p| file_model_id(Path::new(p)).is_some_and(|id| !upstream_ids.contains(&id)))
+        .cloned()
+        .collect();
+    report.local_only.sort();
+    report.files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(report)
+}
+
+/// Copy every new and changed file in `report` into the library, returning how many were
+/// written.  If the library has a manifest, it is then updated to match the SMDX files.
+pub fn apply_import(report: &ImportReport, library: &Path) -> Result<usize, ImportError> {
+    for f in report.files.iter() {
+        let dest = library.join(&f.name);
+        std::fs::copy(&f.source, &dest).map_err(|e| ImportError::Io(dest, e))?;
+    }
+    let manifest = library.join(MANIFEST);
+    if manifest.is_file() {
+        update_manifest(library, &manifest).map_err(ImportError::Manifest)?;
+    }
+    Ok(report.files.len())
+}
+
+#[test]
+fn test_import_models() {
+    let root = std::env::temp_dir().join(format!("sunspec_import_{}", std::process::id()));
+    let (upstream, library) = (root.join("upstream"), root.join("models"));
+    std::fs::create_dir_all(upstream.join("json")).unwrap();
+    std::fs::create_dir_all(upstream.join("smdx")).unwrap();
+    std::fs::create_dir_all(library.join("acme")).unwrap();
+    let model = |id: u16, points: &str| {
+        format!(
+            r#"{{"id": {id}, "group": {{"name": "T", "type": "group", "points": [
+                {{"name": "ID", "type": "uint16", "size": 1, "mandatory": "M", "value": {id}}},
+                {{"name": "L", "type": "uint16", "size": 1, "mandatory": "M"}}{points}]}}}}"#
+        )
+    };
+    let w = r#",{"name": "W", "type": "uint16", "size": 1}"#;
+    let w32 = r#",{"name": "W", "type": "uint32", "size": 2}"#;
+    std::fs::write(library.join("model_65001.json"), model(65001, w)).unwrap();
+    std::fs::write(library.join("model_65002.json"), model(65002, w)).unwrap();
+    std::fs::write(library.join("model_65003.json"), model(65003, w)).unwrap();
+    // a vendor copy of an upstream model, edited
+    std::fs::write(library.join("acme/model_65001.json"), model(65001, w32)).unwrap();
+    // a model the library only has in SMDX, which upstream now has in JSON
+    std::fs::write(
+        library.join("smdx_65006.xml"),
+        r#"<sunSpecModels v="1">
+  <model id="65006" len="1" name="T">
+    <block len="1"><point id="W" offset="0" type="uint16" /></block>
+  </model>
+  <strings id="65006" locale="en"><point id="W"><label>Power</label></point></strings>
+</sunSpecModels>"#,
+    )
+    .unwrap();
+    std::fs::write(library.join("manifest.xml"), "<manifest>\n</manifest>\n").unwrap();
+    std::fs::write(upstream.join("json/model_65001.json"), model(65001, w)).unwrap();
+    std::fs::write(
+        upstream.join("json/model_65002.json"),
+        model(
+            65002,
+            r#",{"name": "W", "type": "uint32", "size": 2},{"name": "VA", "type": "uint16", "size": 1}"#,
+        ),
+    )
+    .unwrap();
+    std::fs::write(upstream.join("json/model_65004.json"), model(65004, w)).unwrap();
+    std::fs::write(upstream.join("json/model_65005.json"), "{").unwrap();
+    std::fs::write(upstream.join("json/model_65006.json"), model(65006, w32)).unwrap();
+    std::fs::write(upstream.join("smdx/manifest.xml"), "<manifest />").unwrap();
+
+    let report = diff_upstream(&upstream, &library).unwrap();
+    assert_eq!(report.unchanged, 1);
+    assert_eq!(report.local_only, vec!["model_65003.json"]);
+    assert_eq!(report.errors.len(), 1);
+    let names: Vec<(&str, FileStatus)> = report
+        .files
+        .iter()
+        .map(|f| (f.name.as_str(), f.status))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("acme/model_65001.json", FileStatus::Changed),
+            ("model_65002.json", FileStatus::Changed),
+            ("model_65004.json", FileStatus::Added),
+            ("model_65006.json", FileStatus::Added),
+        ]
+    );
+    let changed = &report.files[1];
+    assert_eq!(changed.added_points, vec![".T.VA"]);
+    assert!(changed.removed_points.is_empty());
+    assert_eq!(changed.changed_points[0].path, ".T.W");
+    assert_eq!(
+        changed.changed_points[0].changes,
+        vec!["type: uint16 -> uint32", "size: 1 -> 2"]
+    );
+    let converted = &report.files[3];
+    assert_eq!(converted.compared_with.as_deref(), Some("smdx_65006.xml"));
+    assert!(converted.added_points.is_empty());
+    assert_eq!(
+        converted.changed_points[0].changes,
+        vec!["type: uint16 -> uint32", "size: 1 -> 2"]
+    );
+
+    assert_eq!(apply_import(&report, &library).unwrap(), 4);
+    // the library's manifest is kept, and now lists its SMDX file
+    let manifest = std::fs::read_to_string(library.join("manifest.xml")).unwrap();
+    assert!(manifest.contains(r#"name="smdx_65006.xml""#));
+    let report = diff_upstream(&upstream, &library).unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+    assert!(report.files.is_empty());
+    assert_eq!(report.unchanged, 5);
+}
@@ -0,0 +1,206 @@
+//! Integrity checking of the model library against the md5 sums in `models/manifest.xml`.
+use md5::{Digest, Md5};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ManifestError {
+    #[error("Can't read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("Can't parse manifest: {0}")]
+    Parse(String),
+}
+
+#[derive(Deserialize, Debug)]
+struct Manifest {
+    #[serde(rename = "file", default)]
+    files: Vec<ManifestFile>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ManifestFile {
+    md5: String,
+    name: String,
+}
+
+/// The result of checking a model library against its manifest.  File names are relative to
+/// the library directory and use forward slashes, e.g. `generac/smdx_64200.xml`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ManifestReport {
+    /// number of files whose checksum was compared
+    pub checked: usize,
+    /// listed in the manifest, but not found anywhere in the library
+    pub missing: Vec<String>,
+    /// SMDX files in the library that the manifest doesn't list
+    pub extra: Vec<String>,
+    /// files whose md5 doesn't match the manifest
+    pub modified: Vec<String>,
+}
+
+impl ManifestReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.modified.is_empty()
+    }
+}
+
+fn md5_hex(data: &[u8]) -> String {
+    Md5::digest(data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Returns every file under `dir` whose name starts with `smdx`, keyed by file name.  Vendor
+/// subdirectories hold copies of upstream files, so one name can map to several paths.
+fn collect_smdx_files(
+    dir: &Path,
+    prefix: &str,
+    files: &mut BTreeMap<String, Vec<String>>,
+) -> Result<(), ManifestError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| ManifestError::Io(dir.to_path_buf(), e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| ManifestError::Io(dir.to_path_buf(), e))?
+            .path();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        if path.is_dir() {
+            collect_smdx_files(&path, &format!("{prefix}{name}/"), files)?;
+        } else if name.starts_with("smdx") {
+            files
+                .entry(name.clone())
+                .or_default()
+                .push(format!("{prefix}{name}"));
+        }
+    }
+    Ok(())
+}
+
+/// Verify the SMDX files in a model library against the md5 sums in `manifest`.
+///
+/// The manifest comes from the upstream SunSpec repository, where vendor models sit next to the
+/// standard ones; here they live in per-manufacturer subdirectories, so a listed file may be
+/// found at the top level or in any subdirectory, and every copy is checked.  Manifest entries
+/// that aren't SMDX files (such as the upstream CHANGELOG) aren't part of the library and are
+/// ignored.
+///
+/// # Arguments
+///
+/// * `dir` - The model library directory, normally `models`.
+/// * `manifest` - The manifest to check against, normally `models/manifest.xml`.
+pub fn verify_manifest(dir: &Path, manifest: &Path) -> Result<ManifestReport, ManifestError> {
+    let data = std::fs::read(manifest).map_err(|e| ManifestError::Io(manifest.to_path_buf(), e))?;
+    let manifest: Manifest = match serde_xml_rs::from_reader(data.as_slice()) {
+        Ok(m) => m,
+        Err(e) => return Err(ManifestError::Parse(e.to_string())),
+    };
+    let mut on_disk: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    collect_smdx_files(dir, "", &mut on_disk)?;
+
+    let mut report = ManifestReport::default();
+    let mut listed: BTreeSet<&str> = BTreeSet::new();
+    for f in manifest.files.iter().filter(|f| f.name.starts_with("smdx")) {
+        listed.insert(f.name.as_str());
+        let Some(paths) = on_disk.get(&f.name) else {
+            report.missing.push(f.name.clone());
+            continue;
+        };
+        for path in paths.iter() {
+            let data =
+                std::fs::read(dir.join(path)).map_err(|e| ManifestError::Io(dir.join(path), e))?;
+            report.checked += 1;
+            if !md5_hex(&data).eq_ignore_ascii_case(&f.md5) {
+                report.modified.push(path.clone());
+            }
+        }
+    }
+    for (name, paths) in on_disk.iter() {
+        if !listed.contains(name.as_str()) {
+            report.extra.extend(paths.iter().cloned());
+        }
+    }
+    report.missing.sort();
+    report.modified.sort();
+    report.extra.sort();
+    Ok(report)
+}
+
+/// Bring a library's manifest in step with the SMDX files at the top level of the library, e.g.
+/// after importing upstream models: listed files get their current md5 sum, and files it
+/// doesn't list yet are added.  Other entries, such as the upstream CHANGELOG, are kept as they
+/// are, and vendor subdirectories are still checked against the top-level sums.
+///
+/// # Arguments
+///
+/// * `dir` - The model library directory, normally `models`.
+/// * `manifest` - The manifest to update, normally `models/manifest.xml`.
+pub fn update_manifest(dir: &Path, manifest: &Path) -> Result<(), ManifestError> {
+    let data = std::fs::read(manifest).map_err(|e| ManifestError::Io(manifest.to_path_buf(), e))?;
+    let mut files: BTreeMap<String, String> =
+        match serde_xml_rs::from_reader::<_, Manifest>(data.as_slice()) {
+            Ok(m) => m.files.into_iter().map(|f| (f.name, f.md5)).collect(),
+            Err(e) => return Err(ManifestError::Parse(e.to_string())),
+        };
+    let entries = std::fs::read_dir(dir).map_err(|e| ManifestError::Io(dir.to_path_buf(), e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| ManifestError::Io(dir.to_path_buf(), e))?
+            .path();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        if path.is_file() && name.starts_with("smdx") {
+            let data = std::fs::read(&path).map_err(|e| ManifestError::Io(path.clone(), e))?;
+            files.insert(name, md5_hex(&data));
+        }
+    }
+    let mut out = String::from("<manifest>\n");
+    for (name, md5) in files.iter() {
+        out.push_str(&format!("  <file md5=\"{md5}\" name=\"{name}\" />\n"));
+    }
+    out.push_str("</manifest>\n");
+    std::fs::write(manifest, out).map_err(|e| ManifestError::Io(manifest.to_path_buf(), e))
+}
+
+#[test]
+fn test_verify_manifest() {
+    let report = verify_manifest(Path::new("models"), Path::new("models/manifest.xml")).unwrap();
+    assert!(report.is_ok(), "{report:?}");
+    assert!(report.checked > 100);
+
+    let dir = std::env::temp_dir().join(format!("sunspec_manifest_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("acme")).unwrap();
+    std::fs::write(
+        dir.join("manifest.xml"),
+        format!(
+            r#"<manifest>
+  <file md5="{}" name="smdx_00001.xml" />
+  <file md5="{}" name="smdx_00002.xml" />
+  <file md5="00000000000000000000000000000000" name="smdx_00003.xml" />
+</manifest>"#,
+            md5_hex(b"one"),
+            md5_hex(b"two")
+        ),
+    )
+    .unwrap();
+    std::fs::write(dir.join("smdx_00001.xml"), "one").unwrap();
+    std::fs::write(dir.join("acme/smdx_00001.xml"), "edited").unwrap();
+    std::fs::write(dir.join("smdx_00004.xml"), "four").unwrap();
+    let report = verify_manifest(&dir, &dir.join("manifest.xml")).unwrap();
+    assert_eq!(
+        report,
+        ManifestReport {
+            checked: 2,
+            missing: vec!["smdx_00002.xml".into(), "smdx_00003.xml".into()],
+            extra: vec!["smdx_00004.xml".into()],
+            modified: vec!["acme/smdx_00001.xml".into()],
+        }
+    );
+
+    // updating picks up the new file, but files it can't find stay listed
+    std::fs::write(dir.join("acme/smdx_00001.xml"), "one").unwrap();
+    update_manifest(&dir, &dir.join("manifest.xml")).unwrap();
+    let report = verify_manifest(&dir, &dir.join("manifest.xml")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(report.checked, 3);
+    assert!(report.extra.is_empty() && report.modified.is_empty());
+    assert_eq!(report.missing.len(), 2);
+}
@@ -0,0 +1,170 @@
+use crate::json::group::GroupType;
+use crate::json::point::{PointSf, PointType};
+use crate::model_browse::GroupLayout;
+use crate::model_data::ModelData;
+use crate::sunspec_connection::{point_len, PointNode, SunSpecConnection};
+use crate::sunspec_models::ValueType;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A model's decoded data laid out the way the SunSpec JSON model definitions are: a root group
+/// holding points and nested groups, in definition order.  Each instance of a repeated group is
+/// its own entry in `groups`, carrying a 1-based `index`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ModelTree {
+    pub id: u16,
+    pub group: GroupTree,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GroupTree {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: GroupType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub points: Vec<PointLeaf>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GroupTree>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PointLeaf {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: PointType,
+    pub size: i64,
+    /// the unscaled value from the catalog, or null if the point couldn't be read
+    pub value: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub units: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sf: Option<PointSf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl ModelData {
+    /// Build the hierarchical view of this model's decoded data from the point catalog.  Groups
+    /// are laid out from the definition and the counts in the catalog, as ModelData::points
+    /// lays them out, so an instance whose points couldn't be decoded is still there, with null
+    /// values.  Returns None if the model's definition couldn't be loaded as a group tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `catalog` - the point catalog of the connection this model was populated from
+    pub fn tree(&self, catalog: &HashMap<String, PointNode>) -> Option<ModelTree> {
+        if self.model.group.points.is_empty() {
+            return None;
+        }
+        let mut group = build_group(&self.layout(catalog)?, catalog);
+        for p in group.points.iter_mut() {
+            match p.name.as_str() {
+                "ID" => p.value = serde_json::Value::from(self.id),
+                "L" => p.value = serde_json::Value::from(self.len),
+                _ => {}
+            }
+        }
+        Some(ModelTree { id: self.id, group })
+    }
+}
+
+impl SunSpecConnection {
+    /// Build the hierarchical view of a populated model's decoded data.
+    pub fn model_tree(&self, model_id: u16) -> Option<ModelTree> {
+        self.model(model_id)?.tree(&self.catalog.read().unwrap())
+    }
+}
+
+fn build_group(instance: &GroupLayout, catalog: &HashMap<String, PointNode>) -> GroupTree {
+    let group = instance.group;
+    let points = group
+        .points
+        .iter()
+        .map(|p| PointLeaf {
+            name: p.id.clone(),
+            type_: p.r#type,
+            size: point_len(p).unwrap_or(0) as i64,
+            value: match catalog.get(&format!("{}.{}", instance.path, p.id)) {
+                Some(pn) => value_to_json(&pn.value),
+                None => serde_json::Value::Null,
+            },
+            units: p.units.clone(),
+            sf: p.scale_factor.as_ref().map(|sf| match sf.parse::<i64>() {
+                Ok(i) => PointSf::Integer(i),
+                Err(_) => PointSf::String(sf.clone()),
+            }),
+            label: p.literal.as_ref().and_then(|l| l.label.clone()),
+        })
+        .collect();
+    GroupTree {
+        name: group.name.clone(),
+        type_: group.type_,
+        index: instance.index,
+        label: group.label.clone(),
+        points,
+        groups: instance
+            .groups
+            .iter()
+            .map(|g| build_group(g, catalog))
+            .collect(),
+    }
+}
+
+fn value_to_json(value: &ValueType) -> serde_json::Value {
+    match value {
+        ValueType::String(s) => serde_json::Value::from(s.clone()),
+        ValueType::Integer(i) => serde_json::Value::from(*i),
+        ValueType::Float(f) => serde_json::Value::from(*f),
+        ValueType::Boolean(b) => serde_json::Value::from(*b),
+        ValueType::Array(a) => serde_json::Value::from(a.clone()),
+        ValueType::Pad => serde_json::Value::Null,
+    }
+}
+
+#[tokio::test]
+async fn test_model_tree_repeated_groups() {
+    use crate::sunspec_connection::process_group;
+    use crate::sunspec_data::SunSpecData;
+
+    let model = SunSpecData::default().get_model(705, None).unwrap();
+    // registers after ID and L: NPt = 2, NCrv = 2
+    let mut data: Vec<u16> = vec![0; 64];
+    data[3] = 2;
+    data[4] = 2;
+    let mut catalog: HashMap<String, PointNode> = HashMap::new();
+    process_group(&mut data, &model.group, None, &mut 40002, &mut catalog)
+        .await
+        .unwrap();
+
+    let md = ModelData {
+        id: 705,
+        len: model.model.len,
+        address: 40000,
+        model,
+        scale_factors: Default::default(),
+    };
+    let tree = md.tree(&catalog).unwrap();
+    assert_eq!(tree.group.name, "DERVoltVar");
+    assert_eq!(tree.group.points[0].value, serde_json::Value::from(705));
+    assert_eq!(tree.group.groups.len(), 2);
+    assert_eq!(tree.group.groups[1].index, Some(2));
+    assert_eq!(tree.group.groups[1].groups.len(), 2);
+    assert_eq!(tree.group.groups[1].groups[0].name, "Pt");
+
+    let json = serde_json::to_value(&tree).unwrap();
+    assert_eq!(
+        json["group"]["groups"][0]["groups"][1]["points"][0]["name"],
+        "V"
+    );
+
+    // an instance none of whose points could be decoded is still laid out
+    catalog.retain(|path, _| !path.starts_with(".DERVoltVar.Crv[2].Pt[2]."));
+    let tree = md.tree(&catalog).unwrap();
+    let pt = &tree.group.groups[1].groups[1];
+    assert_eq!(pt.index, Some(2));
+    assert!(pt.points.iter().all(|p| p.value.is_null()));
+}
@@ -0,0 +1,443 @@
+use crate::json::group::{Group, GroupCount};
+use crate::json::misc::JSONModel;
+use crate::json::point::{PointSf, PointType, PointValue};
+use crate::sunspec_connection::{point_len, point_type_size, symbol_value};
+use crate::sunspec_models::SmdxFile;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// the model can still be used, but something about it is suspicious
+    Warning,
+    /// the model would be decoded incorrectly, or would make decoding fail
+    Error,
+}
+
+/// A problem found in a model definition.  `path` locates it inside the model, using the same
+/// `.group.subgroup.Point` form as the point catalog; it is empty for problems with the file as a
+/// whole.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match self.path.as_str() {
+            "" => write!(f, "{severity}: {}", self.message),
+            path => write!(f, "{severity}: {path}: {}", self.message),
+        }
+    }
+}
+
+fn issue(severity: Severity, path: &str, message: String) -> ValidationIssue {
+    ValidationIssue {
+        severity,
+        path: path.to_string(),
+        message,
+    }
+}
+
+/// Check a JSON model definition for structural problems.
+pub fn validate_json_model(model: &JSONModel) -> Vec<ValidationIssue> {
+    let mut issues: Vec<ValidationIssue> = vec![];
+    let root = &model.group;
+    let root_path = format!(".{}", root.name);
+    let names: Vec<&str> = root
+        .points
+        .iter()
+        .take(2)
+        .map(|p| p.name.as_str())
+        .collect();
+    if names != ["ID", "L"] {
+        issues.push(issue(
+            Severity::Error,
+            &root_path,
+            String::from("the first two points of a model must be ID and L"),
+        ));
+    }
+    if let Some(PointValue::Integer(id)) = root.points.first().and_then(|p| p.value.as_ref()) {
+        if *id != model.id as i64 {
+            issues.push(issue(
+                Severity::Error,
+                &format!("{root_path}.ID"),
+                format!("ID value {id} doesn't match model id {}", model.id),
+            ));
+        }
+    }
+    if root.points.len() <= 2 && root.groups.is_empty() {
+        issues.push(issue(
+            Severity::Error,
+            &root_path,
+            String::from("model has no points besides ID and L"),
+        ));
+    }
+    validate_json_group(root, &root_path, &[], &mut issues);
+    issues
+}
+
+/// `scopes` holds each enclosing group, outermost first
+fn validate_json_group(
+    group: &Group,
+    path: &str,
+    scopes: &[&Group],
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let mut scopes: Vec<&Group> = scopes.to_vec();
+    scopes.push(group);
+    let in_scope = |name: &str| {
+        scopes
+            .iter()
+            .any(|g| g.points.iter().any(|p| p.name == name))
+    };
+    let mut seen: HashSet<&str> = HashSet::new();
+    for p in group.points.iter() {
+        let point_path = format!("{path}.{}", p.name);
+        if !seen.insert(p.name.as_str()) {
+            issues.push(issue(
+                Severity::Error,
+                &point_path,
+                String::from("point name is used more than once in this group"),
+            ));
+        }
+        if p.size <= 0 {
+            issues.push(issue(
+                Severity::Error,
+                &point_path,
+                format!("size {} must be positive", p.size),
+            ));
+        } else if let Some(size) = point_type_size(&p.type_) {
+            if size as i64 != p.size {
+                issues.push(issue(
+                    Severity::Error,
+                    &point_path,
+                    format!(
+                        "{} is {size} registers, but size is {}",
+                        p.type_.to_string(),
+                        p.size
+                    ),
+                ));
+            }
+        }
+        if let Some(PointSf::String(sf)) = &p.sf {
+            if !in_scope(sf) {
+                issues.push(issue(
+                    Severity::Error,
+                    &point_path,
+                    format!("scale factor {sf} is not a point in this or an enclosing group"),
+                ));
+            }
+        }
+        for s in p.symbols.iter() {
+            // the group tree keeps symbol values as text, which is what the decoder parses
+            let value = s.value.to_string();
+            check_symbol(&p.type_, &point_path, &s.name, &value, issues);
+        }
+    }
+    for g in group.groups.iter() {
+        let group_path = format!("{path}.{}", g.name);
+        match &g.count {
+            // the count has to be decoded before the group, so it can't live inside it
+            GroupCount::String(count) if !in_scope(count) => {
+                issues.push(issue(
+                    Severity::Error,
+                    &group_path,
+                    format!("count {count} is not a point in an enclosing group"),
+                ));
+            }
+            GroupCount::Integer(i) if *i < 0 => {
+                issues.push(issue(
+                    Severity::Error,
+                    &group_path,
+                    format!("count {i} is negative"),
+                ));
+            }
+            _ => {}
+        }
+        validate_json_group(g, &group_path, &scopes, issues);
+    }
+}
+
+/// Check that a symbol of an enum or bitfield point has a value the decoder can match: a
+/// number that fits the enum, or the number of a bit inside the bitfield.  Symbols of other point
+/// types are never matched, so aren't checked.
+fn check_symbol(
+    point_type: &PointType,
+    point_path: &str,
+    name: &str,
+    value: &str,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let max = match point_type {
+        PointType::Enum16 => u16::MAX as u64,
+        PointType::Enum32 => u32::MAX as u64,
+        PointType::Bitfield16 => 15,
+        PointType::Bitfield32 => 31,
+        PointType::Bitfield64 => 63,
+        _ => return,
+    };
+    match symbol_value(value) {
+        None => issues.push(issue(
+            Severity::Error,
+            point_path,
+            format!("symbol {name} has non-numeric value {value:?}"),
+        )),
+        Some(v) if v > max => issues.push(issue(
+            Severity::Error,
+            point_path,
+            format!(
+                "symbol {name} has value {v}, outside a {} point",
+                point_type.to_string()
+            ),
+        )),
+        Some(_) => {}
+    }
+}
+
+/// Check an SMDX model definition for structural problems.
+pub fn validate_smdx_model(smdx: &SmdxFile) -> Vec<ValidationIssue> {
+    let mut issues: Vec<ValidationIssue> = vec![];
+    let model = &smdx.model;
+    // some vendor models don't name themselves
+    let model_path = match model.name.as_str() {
+        "" => format!(".model_{}", model.id),
+        name => format!(".{name}"),
+    };
+    if model.block.is_empty() {
+        issues.push(issue(
+            Severity::Error,
+            &model_path,
+            String::from("model has no blocks"),
+        ));
+        return issues;
+    }
+    let all_points: Vec<&str> = model
+        .block
+        .iter()
+        .flat_map(|b| b.point.iter().map(|p| p.id.as_str()))
+        .collect();
+    for (idx, block) in model.block.iter().enumerate() {
+        let block_path = match &block.name {
+            Some(name) => format!("{model_path}.{name}"),
+            None => format!("{model_path}.block[{idx}]"),
+        };
+        let mut end: u16 = 0;
+        let mut seen: HashSet<&str> = HashSet::new();
+        for p in block.point.iter() {
+            let point_path = format!("{block_path}.{}", p.id);
+            if !seen.insert(p.id.as_str()) {
+                issues.push(issue(
+                    Severity::Error,
+                    &point_path,
+                    String::from("point id is used more than once in this block"),
+                ));
+            }
+            let len = match point_len(p) {
+                Some(len) => len,
+                None => {
+                    issues.push(issue(
+                        Severity::Error,
+                        &point_path,
+                        format!(
+                            "can't determine the length of a {} point",
+                            p.r#type.to_string()
+                        ),
+                    ));
+                    continue;
+                }
+            };
+            end = end.max(p.offset + len);
+            if let Some(sf) = &p.scale_factor {
+                if sf.parse::<i16>().is_err() && !all_points.contains(&sf.as_str()) {
+                    issues.push(issue(
+                        Severity::Error,
+                        &point_path,
+                        format!("scale factor {sf} is not a point in this model"),
+                    ));
+                }
+            }
+            for s in p.symbol.iter().flatten() {
+                check_symbol(&p.r#type, &point_path, &s.id, &s.symbol, &mut issues);
+            }
+        }
+        if end > block.len {
+            issues.push(issue(
+                Severity::Error,
+                &block_path,
+                format!(
+                    "points occupy {end} registers, but the block is {} long",
+                    block.len
+                ),
+            ));
+        } else if end < block.len {
+            issues.push(issue(
+                Severity::Warning,
+                &block_path,
+                format!(
+                    "points occupy {end} registers, but the block is {} long",
+                    block.len
+                ),
+            ));
+        }
+    }
+    let (fixed_len, repeat_len) = match model.block[0].r#type.as_deref() {
+        Some("repeating") => (0, Some(model.block[0].len)),
+        _ => (model.block[0].len, model.block.get(1).map(|b| b.len)),
+    };
+    let fits = match repeat_len {
+        Some(0) => false,
+        Some(r) => model.len >= fixed_len && (model.len - fixed_len).is_multiple_of(r),
+        None => model.len == fixed_len,
+    };
+    if !fits {
+        issues.push(issue(
+            Severity::Error,
+            &model_path,
+            format!("model length {} doesn't match its block lengths", model.len),
+        ));
+    }
+    // the definition is loaded through the JSON form, so one that can't be converted (e.g. with
+    // overlapping points) fails to load
+    if !issues.iter().any(|i| i.severity == Severity::Error) {
+        if let Err(e) = JSONModel::try_from(smdx) {
+            issues.push(issue(Severity::Error, &model_path, e.to_string()));
+        }
+    }
+    issues
+}
+
+/// The lint result for one model definition file.
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    pub file: PathBuf,
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl FileReport {
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|i| i.severity == Severity::Error)
+    }
+}
+
+/// Returns the model id a file name claims, for `model_<id>.json` and `smdx_<id>.xml`.
+pub(crate) fn file_model_id(file: &Path) -> Option<u16> {
+    let name = file.file_name()?.to_str()?;
+    let id = if let Some(id) = name.strip_prefix("model_") {
+        id.strip_suffix(".json")?
+    } else {
+        name.strip_prefix("smdx_")?.strip_suffix(".xml")?
+    };
+    id.parse().ok()
+}
+
+/// Parse and validate a single JSON or SMDX model file.  A file that can't be read or parsed
+/// is reported as a single error.
+pub fn lint_file(file: &Path) -> FileReport {
+    let mut report = FileReport {
+        file: file.to_path_buf(),
+        issues: vec![],
+    };
+    let data = match std::fs::read(file) {
+        Ok(d) => d,
+        Err(e) => {
+            report
+                .issues
+                .push(issue(Severity::Error, "", format!("can't read file: {e}")));
+            return report;
+        }
+    };
+    let is_json = file.extension().is_some_and(|e| e == "json");
+    let parsed = if is_json {
+        serde_json::from_slice::<JSONModel>(&data)
+            .map(|m| (m.id, validate_json_model(&m)))
+            .map_err(|e| format!("can't parse JSON: {e}"))
+    } else {
+        serde_xml_rs::from_reader::<_, SmdxFile>(data.as_slice())
+            .map(|m| (m.model.id, validate_smdx_model(&m)))
+            .map_err(|e| format!("can't parse SMDX: {e}"))
+    };
+    match parsed {
+        Ok((id, issues)) => {
+            // the loader finds models by file name, so a mismatch serves the wrong definition
+            if let Some(file_id) = file_model_id(file) {
+                if file_id != id {
+                    report.issues.push(issue(
+                        Severity::Error,
+                        "",
+                        format!("file is named for model {file_id}, but defines model {id}"),
+                    ));
+                }
+            }
+            report.issues.extend(issues);
+        }
+        Err(e) => report.issues.push(issue(Severity::Error, "", e)),
+    }
+    report
+}
+
+/// Lint every `model_<id>.json` and `smdx_<id>.xml` file under `dir`, including vendor
+/// subdirectories.  Reports are sorted by file path.
+pub fn lint_directory(dir: &Path) -> std::io::Result<Vec<FileReport>> {
+    let mut files: Vec<PathBuf> = vec![];
+    collect_model_files(dir, &mut files)?;
+    files.sort();
+    Ok(files.iter().map(|f| lint_file(f)).collect())
+}
+
+fn collect_model_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_model_files(&path, files)?;
+        } else if file_model_id(&path).is_some() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_validate_json_model() {
+    let model: JSONModel = serde_json::from_str(
+        r#"{"id": 65000, "group": {"name": "T", "type": "group", "points": [
+            {"name": "ID", "type": "uint16", "size": 1},
+            {"name": "L", "type": "uint16", "size": 1},
+            {"name": "W", "type": "int32", "size": 1, "sf": "W_SF"},
+            {"name": "St", "type": "enum16", "size": 1,
+             "symbols": [{"name": "ON", "value": "one"}]},
+            {"name": "B", "type": "bitfield16", "size": 1,
+             "symbols": [{"name": "FAULT", "value": 15}, {"name": "OTHER", "value": 16}]}],
+          "groups": [{"name": "G", "type": "group", "count": "N",
+            "points": [{"name": "V", "type": "uint16", "size": 1}]}]}}"#,
+    )
+    .unwrap();
+    let issues = validate_json_model(&model);
+    let paths: Vec<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+    // W has the wrong size and a missing sf, St a bad symbol, B a bit it doesn't have, G a
+    // missing count
+    assert_eq!(paths, vec![".T.W", ".T.W", ".T.St", ".T.B", ".T.G"]);
+    assert!(issues.iter().all(|i| i.severity == Severity::Error));
+}
+
+#[test]
+fn test_lint_directory() {
+    let reports = lint_directory(Path::new("models")).unwrap();
+    assert!(reports.len() > 200);
+    // every bundled definition at least parses
+    assert!(reports
+        .iter()
+        .flat_map(|r| r.issues.iter())
+        .all(|i| !i.message.starts_with("can't parse")));
+    let bad: Vec<&FileReport> = reports.iter().filter(|r| r.has_errors()).collect();
+    assert!(bad
+        .iter()
+        .any(|r| r.file.ends_with("generac/smdx_64260.xml")));
+}
@@ -0,0 +1,504 @@
+use crate::sunspec_connection::{
+    decode, decode_point_value, find_in_scope, point_len, scale_value, SunSpecConnection,
+    SunSpecPointError, Word,
+};
+use crate::sunspec_models::{Point, PointIdentifier, ValueType};
+use bon::Builder;
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_modbus::Address;
+
+/// Largest number of unused registers we'll read through to join two due points into one request.
+pub const DEFAULT_MAX_GAP: u16 = 8;
+/// Largest single coalesced read, in registers.
+pub const DEFAULT_MAX_BATCH: u16 = 100;
+/// How many events the broadcast channel buffers before slow receivers start lagging.
+pub const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+pub type SubscriptionId = usize;
+
+/// The outcome of one coalesced read: start address, length, and the registers (or the error).
+type ReadBlock = (Address, u16, Result<Vec<Word>, String>);
+
+/// A request to poll a single point on a fixed interval.
+#[derive(Debug, Clone, Builder)]
+pub struct Subscription {
+    /// the model the point belongs to (must already be populated on the connection)
+    pub model_id: u16,
+    /// the point to poll, either by name or catalog path
+    pub point: PointIdentifier,
+    /// how often the point should be read
+    pub interval: Duration,
+    /// for numeric points, the minimum change from the last reported value that produces an event
+    pub deadband: Option<f64>,
+}
+
+/// What the poller should do when a subscription is due more than one interval in the past,
+/// e.g. because the device is answering slowly.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LagBehavior {
+    /// drop the missed polls and resume on the original schedule
+    #[default]
+    Skip,
+    /// poll back-to-back until the schedule has caught up
+    Burst,
+    /// drop the missed polls and restart the schedule from now
+    Delay,
+}
+
+#[derive(Debug, Clone, Builder)]
+pub struct PollerConfig {
+    #[builder(default = DEFAULT_MAX_GAP)]
+    pub max_gap: u16,
+    #[builder(default = DEFAULT_MAX_BATCH)]
+    pub max_batch: u16,
+    #[builder(default)]
+    pub lag_behavior: LagBehavior,
+    #[builder(default = DEFAULT_EVENT_CAPACITY)]
+    pub event_capacity: usize,
+}
+
+impl Default for PollerConfig {
+    fn default() -> Self {
+        PollerConfig::builder().build()
+    }
+}
+
+/// A new value for a subscribed point.
+#[derive(Debug, Clone)]
+pub struct PointUpdate {
+    pub id: SubscriptionId,
+    pub model_id: u16,
+    pub point: PointIdentifier,
+    pub value: ValueType,
+    /// the previously reported value, if any
+    pub previous: Option<ValueType>,
+    pub timestamp: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+pub enum PollEvent {
+    /// the point's value changed (by more than its deadband, if any)
+    Changed(PointUpdate),
+    /// the point couldn't be read or decoded on this cycle
+    Failed {
+        id: SubscriptionId,
+        point: PointIdentifier,
+        error: String,
+    },
+    /// the point was due more than one interval ago; `missed` polls were not performed on time
+    Behind {
+        id: SubscriptionId,
+        point: PointIdentifier,
+        missed: u32,
+    },
+}
+
+#[derive(Debug)]
+struct SubscriptionState {
+    subscription: Subscription,
+    point: Point,
+    address: Address,
+    len: u16,
+    scale_factor: Option<Address>,
+    next_due: Instant,
+    last: Option<ValueType>,
+}
+
+/// Polls a set of subscribed points, batching the points that are due at the same time into
+/// coalesced register reads, and publishes changes on a broadcast channel.
+pub struct Poller {
+    conn: SunSpecConnection,
+    config: PollerConfig,
+    subscriptions: Vec<SubscriptionState>,
+    events: broadcast::Sender<PollEvent>,
+}
+
+impl Poller {
+    /// Create a new poller for an already-populated connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - A SunSpecConnection whose models (and catalog) have been populated
+    /// * `config` - batching, lag and channel settings
+    pub fn new(conn: SunSpecConnection, config: PollerConfig) -> Self {
+        let (events, _) = broadcast::channel(config.event_capacity);
+        Poller {
+            conn,
+            config,
+            subscriptions: vec![],
+            events,
+        }
+    }
+
+    /// Add a point to the poll schedule.  The point (and its scale factor) are resolved up front,
+    /// so a bad subscription is reported here rather than on every cycle.
+    pub fn subscribe(
+        &mut self,
+        subscription: Subscription,
+    ) -> Result<SubscriptionId, SunSpecPointError> {
+        if subscription.interval.is_zero() {
+            return Err(SunSpecPointError::GeneralError(String::from(
+                "poll interval must be greater than zero",
+            )));
+        }
+        let md = match self.conn.model(subscription.model_id) {
+            Some(md) => md,
+            None => {
+                return Err(SunSpecPointError::DoesNotExist(format!(
+                    "model {} is not present on this connection",
+                    subscription.model_id
+                )));
+            }
+        };
+        let (point, address) = self.conn.resolve_point(&md, &subscription.point)?;
+        let len = match point_len(&point) {
+            Some(len) => len,
+            None => {
+                return Err(SunSpecPointError::GeneralError(format!(
+                    "can't determine register length of {}",
+                    subscription.point
+                )));
+            }
+        };
+        let scale_factor = match point.scale_factor.clone() {
+            None => None,
+            Some(sf) => {
+                let sf_identifier = match &subscription.point {
+                    PointIdentifier::Catalog(path) => {
+                        find_in_scope(&self.conn.catalog.read().unwrap(), path, &sf)
+                            .map(PointIdentifier::Catalog)
+                    }
+                    PointIdentifier::Point(_) => Some(PointIdentifier::Point(sf.clone())),
+                    PointIdentifier::Repeating(n, _) => match md.repeating_group() {
+                        Some((_, g)) if g.points.iter().any(|p| p.id == sf) => {
+                            Some(PointIdentifier::Repeating(*n, sf.clone()))
+                        }
+                        _ => Some(PointIdentifier::Point(sf.clone())),
+                    },
+                };
+                match sf_identifier.map(|i| self.conn.resolve_point(&md, &i)) {
+                    Some(Ok((_, sf_address))) => Some(sf_address),
+                    _ => {
+                        warn!(
+                            "Can't resolve scale factor {sf} for {}, values will be unscaled",
+                            subscription.point
+                        );
+                        None
+                    }
+                }
+            }
+        };
+        self.subscriptions.push(SubscriptionState {
+            subscription,
+            point,
+            address,
+            len,
+            scale_factor,
+            next_due: Instant::now(),
+            last: None,
+        });
+        Ok(self.subscriptions.len() - 1)
+    }
+
+    /// Get a receiver for the change stream.  Receivers only see events sent after they subscribe.
+    pub fn events(&self) -> broadcast::Receiver<PollEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns when the next subscription is due, or None if nothing is subscribed.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.subscriptions.iter().map(|s| s.next_due).min()
+    }
+
+    /// Poll every subscription that is due now, in as few reads as possible, and publish the
+    /// resulting events.  Returns the number of subscriptions that were polled.
+    pub async fn poll_due(&mut self) -> usize {
+        let now = Instant::now();
+        let mut due: Vec<usize> = vec![];
+        for (id, state) in self.subscriptions.iter_mut().enumerate() {
+            if state.next_due > now {
+                continue;
+            }
+            let interval = state.subscription.interval;
+            let missed = ((now - state.next_due).as_nanos() / interval.as_nanos()) as u32;
+            if missed > 0 {
+                let _ = self.events.send(PollEvent::Behind {
+                    id,
+                    point: state.subscription.point.clone(),
+                    missed,
+                });
+            }
+            state.next_due = match self.config.lag_behavior {
+                LagBehavior::Skip => state.next_due + interval * (missed + 1),
+                LagBehavior::Burst => state.next_due + interval,
+                LagBehavior::Delay if missed > 0 => now + interval,
+                LagBehavior::Delay => state.next_due + interval,
+            };
+            due.push(id);
+        }
+        if due.is_empty() {
+            return 0;
+        }
+
+        let mut wanted: Vec<(Address, u16)> = vec![];
+        for id in due.iter() {
+            let state = &self.subscriptions[*id];
+            wanted.push((state.address, state.len));
+            if let Some(sf_address) = state.scale_factor {
+                wanted.push((sf_address, 1));
+            }
+        }
+        let mut blocks: Vec<ReadBlock> = vec![];
+        for (start, len) in coalesce(wanted, self.config.max_gap, self.config.max_batch) {
+            let result = self
+                .conn
+                .get_raw(start, len)
+                .await
+                .map_err(|e| e.to_string());
+            blocks.push((start, len, result));
+        }
+
+        for id in due.iter() {
+            let event = self.evaluate(*id, &blocks);
+            if let Some(event) = event {
+                let _ = self.events.send(event);
+            }
+        }
+        due.len()
+    }
+
+    /// Poll forever, sleeping until the next subscription is due.  Returns once there is
+    /// nothing subscribed.
+    pub async fn run(mut self) {
+        while let Some(next) = self.next_due() {
+            tokio::time::sleep_until(next).await;
+            self.poll_due().await;
+        }
+    }
+
+    /// Run the poller on its own task.
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    fn evaluate(&mut self, id: SubscriptionId, blocks: &[ReadBlock]) -> Option<PollEvent> {
+        let strict_symbol = self.conn.strict_symbol;
+        let state = &mut self.subscriptions[id];
+        let failed = |error: String| PollEvent::Failed {
+            id,
+            point: state.subscription.point.clone(),
+            error,
+        };
+        let data = match registers_at(blocks, state.address, state.len) {
+            Ok(data) => data,
+            Err(e) => return Some(failed(e)),
+        };
+        let mut value = match decode_point_value(&state.point, data, strict_symbol) {
+            Ok(v) => v,
+            Err(e) => return Some(failed(e.to_string())),
+        };
+        if let Some(sf_address) = state.scale_factor {
+            match registers_at(blocks, sf_address, 1) {
+                Ok(sf) => {
+                    // an unimplemented scale factor leaves the value unscaled, as in get_point
+                    if let Ok(sf) = decode::int16(sf) {
+                        value = scale_value(value, sf);
+                    }
+                }
+                Err(e) => return Some(failed(e)),
+            }
+        }
+        if !has_changed(state.last.as_ref(), &value, state.subscription.deadband) {
+            return None;
+        }
+        let previous = state.last.replace(value.clone());
+        Some(PollEvent::Changed(PointUpdate {
+            id,
+            model_id: state.subscription.model_id,
+            point: state.subscription.point.clone(),
+            value,
+            previous,
+            timestamp: SystemTime::now(),
+        }))
+    }
+}
+
+/// Merge a set of (address, length) reads into as few reads as possible, joining reads that are
+/// separated by at most `max_gap` registers as long as the result is no longer than `max_batch`.
+pub(crate) fn coalesce(
+    mut reads: Vec<(Address, u16)>,
+    max_gap: u16,
+    max_batch: u16,
+) -> Vec<(Address, u16)> {
+    reads.sort();
+    let mut merged: Vec<(Address, u16)> = vec![];
+    for (start, len) in reads {
+        if let Some((cur_start, cur_len)) = merged.last_mut() {
+            let cur_end = *cur_start as u32 + *cur_len as u32;
+            let end = start as u32 + len as u32;
+            if start as u32 <= cur_end + max_gap as u32
+                && end.max(cur_end) - *cur_start as u32 <= max_batch as u32
+            {
+                *cur_len = (end.max(cur_end) - *cur_start as u32) as u16;
+                continue;
+            }
+        }
+        merged.push((start, len));
+    }
+    merged
+}
+
+fn registers_at(blocks: &[ReadBlock], address: Address, len: u16) -> Result<&[Word], String> {
+    for (start, block_len, result) in blocks.iter() {
+        if address < *start || address as u32 + len as u32 > *start as u32 + *block_len as u32 {
+            continue;
+        }
+        let offset = (address - start) as usize;
+        return match result {
+            Ok(data) if offset + len as usize <= data.len() => {
+                Ok(&data[offset..offset + len as usize])
+            }
+            Ok(data) => Err(format!(
+                "short read at {start}: expected {block_len} registers, got {}",
+                data.len()
+            )),
+            Err(e) => Err(e.clone()),
+        };
+    }
+    Err(format!("no data read for address {address}"))
+}
+
+fn has_changed(previous: Option<&ValueType>, value: &ValueType, deadband: Option<f64>) -> bool {
+    let previous = match previous {
+        None => return true,
+        Some(p) => p,
+    };
+    match (as_f64(previous), as_f64(value), deadband) {
+        (Some(a), Some(b), Some(db)) => (a - b).abs() > db,
+        _ => previous != value,
+    }
+}
+
+fn as_f64(value: &ValueType) -> Option<f64> {
+    match value {
+        ValueType::Integer(i) => Some(*i as f64),
+        ValueType::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_coalesce() {
+    let reads = vec![(40010, 2), (40000, 1), (40003, 1), (40200, 4), (40011, 1)];
+    assert_eq!(
+        coalesce(reads, 4, 100),
+        vec![(40000, 4), (40010, 2), (40200, 4)]
+    );
+    assert_eq!(
+        coalesce(vec![(0, 60), (60, 60)], 0, 100),
+        vec![(0, 60), (60, 60)]
+    );
+}
+
+#[test]
+fn test_deadband() {
+    let prev = ValueType::Float(230.0);
+    assert!(!has_changed(
+        Some(&prev),
+        &ValueType::Float(230.4),
+        Some(0.5)
+    ));
+    assert!(has_changed(
+        Some(&prev),
+        &ValueType::Float(231.0),
+        Some(0.5)
+    ));
+    assert!(has_changed(Some(&prev), &ValueType::Float(230.1), None));
+    assert!(has_changed(None, &ValueType::Float(230.0), Some(0.5)));
+}
+
+// paused time only moves on when the test sleeps, so the schedule comes out exact
+#[tokio::test(start_paused = true)]
+async fn test_poll_due() {
+    use crate::modbus_test_harness::ModbusTestHarness;
+    use crate::sunspec_data::SunSpecData;
+    use std::collections::HashMap;
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    // the harness's common model, followed by an inverter (model 103) with A=100 and A_SF=-1
+    let registers: HashMap<Address, Word> = HashMap::from([
+        (40070, 103),
+        (40071, 50),
+        (40072, 100),
+        (40076, -1_i16 as u16),
+        (40122, 0xffff),
+        (40123, 0),
+    ]);
+    let amps = |event: Result<PollEvent, TryRecvError>| match event {
+        Ok(PollEvent::Changed(PointUpdate {
+            value: ValueType::Float(a),
+            ..
+        })) => a,
+        other => panic!("expected a scaled change, got {other:?}"),
+    };
+    let interval = Duration::from_millis(20);
+    for behavior in [LagBehavior::Skip, LagBehavior::Burst, LagBehavior::Delay] {
+        let harness = ModbusTestHarness {
+            buf: vec![0],
+            registers: registers.clone(),
+            ..Default::default()
+        };
+        let conn = SunSpecConnection::test_new(harness, false).await.unwrap();
+        conn.populate_models(&SunSpecData::default()).await.unwrap();
+        let config = PollerConfig::builder().lag_behavior(behavior).build();
+        let mut poller = Poller::new(conn.clone(), config);
+        let mut events = poller.events();
+        let subscription = Subscription::builder()
+            .model_id(103)
+            .point(PointIdentifier::Point(String::from("A")))
+            .interval(interval)
+            .deadband(0.5)
+            .build();
+        let id = poller.subscribe(subscription).unwrap();
+        let start = poller.next_due().unwrap();
+
+        assert_eq!(poller.poll_due().await, 1);
+        assert!((amps(events.try_recv()) - 10.0).abs() < 1e-9);
+
+        // a change inside the deadband is polled but not reported
+        conn.set_u16(40072, 102).await.unwrap();
+        tokio::time::sleep_until(poller.next_due().unwrap()).await;
+        assert_eq!(poller.poll_due().await, 1);
+        assert!(matches!(events.try_recv(), Err(TryRecvError::Empty)));
+
+        // fall three and a half intervals behind
+        conn.set_u16(40072, 110).await.unwrap();
+        tokio::time::sleep_until(start + interval * 2 + interval * 7 / 2).await;
+        let before = Instant::now();
+        assert_eq!(poller.poll_due().await, 1);
+        let missed = match events.try_recv() {
+            Ok(PollEvent::Behind {
+                id: behind, missed, ..
+            }) if behind == id => missed,
+            other => panic!("expected {behavior:?} to report falling behind, got {other:?}"),
+        };
+        assert_eq!(missed, 3);
+        assert!((amps(events.try_recv()) - 11.0).abs() < 1e-9);
+
+        let next_due = poller.next_due().unwrap();
+        match behavior {
+            // back on the original schedule
+            LagBehavior::Skip => assert_eq!(next_due, start + interval * 6),
+            // still behind, so the next poll happens straight away
+            LagBehavior::Burst => {
+                assert_eq!(next_due, start + interval * 3);
+                assert_eq!(poller.poll_due().await, 1);
+            }
+            // a new schedule, starting from the late poll
+            LagBehavior::Delay => assert_eq!(next_due, before + interval),
+        }
+    }
+}
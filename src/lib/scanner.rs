@@ -0,0 +1,304 @@
+use crate::device_identity::DeviceIdentity;
+use crate::discovery::DiscoveryLimits;
+use crate::sunspec_connection::{SunSpecConnection, Word};
+use crate::sunspec_data::SunSpecData;
+use bon::Builder;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::{timeout_at, Instant};
+use tokio_modbus::{Address, Slave};
+
+/// The registers a SunSpec device's data block starts with: `SunS`.
+pub const SUNSPEC_MARKER: [Word; 2] = [0x5375, 0x6e53];
+/// The base registers a SunSpec data block may start at, in the order the spec prefers them.
+pub const SUNSPEC_BASE_ADDRESSES: [Address; 3] = [40000, 50000, 0];
+pub const DEFAULT_FIRST_SLAVE: u8 = 1;
+pub const DEFAULT_LAST_SLAVE: u8 = 247;
+pub const DEFAULT_SCAN_CONCURRENCY: usize = 8;
+pub const DEFAULT_PROBE_TIMEOUT_MS: u64 = 500_u64;
+
+/// What to scan, and how hard.
+#[derive(Debug, Clone, Builder)]
+pub struct ScanOptions {
+    #[builder(default = DEFAULT_FIRST_SLAVE)]
+    pub first_slave: u8,
+    /// the last slave id probed, inclusive
+    #[builder(default = DEFAULT_LAST_SLAVE)]
+    pub last_slave: u8,
+    /// how many slaves are scanned at once; their requests still take turns on the one socket
+    #[builder(default = DEFAULT_SCAN_CONCURRENCY)]
+    pub concurrency: usize,
+    /// how long to wait for a slave to answer the marker probe, at all of the base addresses
+    #[builder(default = Duration::from_millis(DEFAULT_PROBE_TIMEOUT_MS))]
+    pub probe_timeout: Duration,
+    /// limits for discovering the models of each slave found
+    #[builder(default)]
+    pub limits: DiscoveryLimits,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions::builder().build()
+    }
+}
+
+/// A SunSpec device found by a scan.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ScanResult {
+    pub slave: u8,
+    /// the address of the `SunS` marker
+    pub base: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<DeviceIdentity>,
+    /// the ids of the models found, in address order
+    pub models: Vec<u16>,
+    /// whether model discovery reached the end marker
+    pub complete: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    /// the lowest slave id the same device was found on, if it answers on several
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<u8>,
+}
+
+impl SunSpecConnection {
+    /// Probe a range of slave ids behind this connection's socket for SunSpec devices, and
+    /// discover the models and identity of each one found.  Probes share the one socket and
+    /// are serialized on it, so a slave that doesn't answer holds up every other slave for the
+    /// probe timeout; concurrency only lets one slave's requests go out between another's.
+    /// Results are sorted by slave id; a device answering on several ids is marked as a
+    /// duplicate of the first.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - the SunSpecData library to load model definitions from
+    /// * `options` - which slaves to probe, how many at once and how long to wait
+    pub async fn scan_slaves(&self, data: &SunSpecData, options: &ScanOptions) -> Vec<ScanResult> {
+        self.scan_slave_ids(data, options.first_slave..=options.last_slave, options)
+            .await
+    }
+
+    /// scan_slaves, for a list of slave ids rather than the range in `options`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - the SunSpecData library to load model definitions from
+    /// * `slaves` - the slave ids to probe
+    /// * `options` - how many slaves to probe at once and how long to wait
+    pub async fn scan_slave_ids(
+        &self,
+        data: &SunSpecData,
+        slaves: impl IntoIterator<Item = u8>,
+        options: &ScanOptions,
+    ) -> Vec<ScanResult> {
+        let permits = Arc::new(Semaphore::new(options.concurrency.max(1)));
+        let mut tasks: JoinSet<Option<ScanResult>> = JoinSet::new();
+        for slave in slaves {
+            let conn = self.for_slave(slave);
+            let data = data.clone();
+            let options = options.clone();
+            let permits = permits.clone();
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await.ok()?;
+                conn.scan_slave(&data, &options).await
+            });
+        }
+        let mut results: Vec<ScanResult> = vec![];
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok(Some(result)) => results.push(result),
+                Ok(None) => {}
+                Err(e) => warn!("[{}] scan task failed: {e}", self.addr),
+            }
+        }
+        results.sort_by_key(|r| r.slave);
+        for i in 0..results.len() {
+            let Some(identity) = results[i].identity.clone() else {
+                continue;
+            };
+            results[i].duplicate_of = results[..i]
+                .iter()
+                .find(|r| {
+                    r.duplicate_of.is_none()
+                        && r.identity
+                            .as_ref()
+                            .is_some_and(|id| id.is_same_device(&identity))
+                })
+                .map(|r| r.slave);
+        }
+        results
+    }
+
+    /// Probe this connection's slave and, if it is a SunSpec device, discover its models.
+    async fn scan_slave(&self, data: &SunSpecData, options: &ScanOptions) -> Option<ScanResult> {
+        let slave = self.slave_num?;
+        let base = self.probe_marker(options.probe_timeout).await?;
+        info!(
+            "[{}] slave {slave} has a SunSpec marker at {base}",
+            self.addr
+        );
+        let report = self
+            .discover_models_at(
+                data,
+                base + SUNSPEC_MARKER.len() as Address,
+                &options.limits,
+            )
+            .await;
+        let mut models: Vec<(Address, u16)> = report
+            .models
+            .values()
+            .map(|md| (md.address, md.id))
+            .collect();
+        models.sort();
+        Some(ScanResult {
+            slave,
+            base,
+            identity: report.identity,
+            models: models.into_iter().map(|(_, id)| id).collect(),
+            complete: report.complete,
+            errors: report.errors.iter().map(|e| e.to_string()).collect(),
+            duplicate_of: None,
+        })
+    }
+
+    /// Look for the `SunS` marker at each base register, returning the base it was found at.
+    /// Reads go straight to the socket with no retries, as most slave ids won't answer at all.
+    /// The probe holds the socket for its duration, so probes of slaves sharing it run one at a
+    /// time, and all of a probe's reads share `probe_timeout`.  A slave answering too late would
+    /// leave its reply on the socket to be mistaken for the next one, so the connection is made
+    /// again after a timeout.
+    async fn probe_marker(&self, probe_timeout: Duration) -> Option<Address> {
+        let mut ctx = self.ctx.lock().await;
+        ctx.set_slave(self.slave_num.map_or(Slave::tcp_device(), Slave));
+        let deadline = Instant::now() + probe_timeout;
+        for base in SUNSPEC_BASE_ADDRESSES {
+            match timeout_at(deadline, ctx.read_holding_registers(base, 2)).await {
+                Ok(Ok(words)) if words == SUNSPEC_MARKER => return Some(base),
+                // an exception or other data: something is there, but not at this base
+                Ok(_) => continue,
+                Err(_) => {
+                    debug!("[{}] no answer from slave {:?}", self.addr, self.slave_num);
+                    if let Err(e) = self.reconnect(&mut ctx).await {
+                        warn!(
+                            "[{}] can't reconnect after a probe timed out: {e}",
+                            self.addr
+                        );
+                    }
+                    return None;
+                }
+            }
+        }
+        None
+    }
+}
+
+#[tokio::test]
+async fn test_scan_slaves() {
+    use crate::modbus_test_harness::ModbusTestHarness;
+
+    let data = SunSpecData::default();
+    let options = ScanOptions::builder()
+        .first_slave(1)
+        .last_slave(3)
+        .concurrency(2)
+        .build();
+
+    // the harness answers the same for every slave, and its marker registers hold no marker
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: vec![0, 0],
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    assert!(conn.scan_slaves(&data, &options).await.is_empty());
+
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: SUNSPEC_MARKER.to_vec(),
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    let results = conn.scan_slaves(&data, &options).await;
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].slave, 1);
+    assert_eq!(results[0].base, 40000);
+    assert_eq!(results[0].models, vec![1]);
+    assert!(results[0].complete);
+    assert_eq!(
+        results[0].identity.as_ref().unwrap().serial_number,
+        "1234567890"
+    );
+    assert_eq!(results[0].duplicate_of, None);
+    assert_eq!(results[1].duplicate_of, Some(1));
+    assert_eq!(results[2].duplicate_of, Some(1));
+    // scanning doesn't disturb the connection it was run from
+    assert!(conn.models().is_empty());
+
+    let json = serde_json::to_value(&results).unwrap();
+    assert_eq!(json[0]["identity"]["manufacturer"], "Test");
+    assert!(json[0].get("duplicate_of").is_none());
+    assert_eq!(json[2]["duplicate_of"], 1);
+
+    // probes are addressed to the slave scanned, and the connection's own reads go back to its
+    // own slave id, or the default unit id without one, rather than the last slave probed
+    let slave = Arc::new(std::sync::Mutex::new(None));
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: SUNSPEC_MARKER.to_vec(),
+            slave: slave.clone(),
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    let options = ScanOptions::builder().first_slave(2).last_slave(2).build();
+    assert_eq!(conn.scan_slaves(&data, &options).await.len(), 1);
+    assert_eq!(*slave.lock().unwrap(), Some(2));
+    conn.get_raw(40000, 2).await.unwrap();
+    assert_eq!(*slave.lock().unwrap(), Some(0));
+    conn.for_slave(2).get_raw(40000, 2).await.unwrap();
+    let conn = SunSpecConnection {
+        slave_num: None,
+        ..conn
+    };
+    conn.get_raw(40000, 2).await.unwrap();
+    assert_eq!(*slave.lock().unwrap(), Some(Slave::tcp_device().0));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_scan_after_discovery_timeout() {
+    use crate::modbus_test_harness::ModbusTestHarness;
+    use std::collections::HashSet;
+
+    // slave 2 answers too late, leaving its reply on the socket the other slaves share
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: vec![0],
+            silent: HashSet::from([2]),
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    let data = SunSpecData::default();
+    let limits = DiscoveryLimits::default();
+    let report = conn.for_slave(2).discover_models(&data, &limits).await;
+    assert!(report.models.is_empty());
+    assert!(!report.complete);
+
+    let report = conn.for_slave(1).discover_models(&data, &limits).await;
+    assert!(report.complete, "{:?}", report.errors);
+    assert!(report.models.contains_key(&1));
+    assert_eq!(report.identity.unwrap().serial_number, "1234567890");
+}
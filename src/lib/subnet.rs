@@ -0,0 +1,208 @@
+use crate::discovery::DiscoveryLimits;
+use crate::scanner::{ScanOptions, ScanResult, DEFAULT_PROBE_TIMEOUT_MS};
+use crate::sunspec_connection::SunSpecConnection;
+use crate::sunspec_data::SunSpecData;
+use bon::Builder;
+use serde::Serialize;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::{JoinError, JoinSet};
+use tokio::time::timeout;
+
+pub const DEFAULT_MODBUS_PORT: u16 = 502;
+/// Unit ids SunSpec devices commonly answer on.
+pub const DEFAULT_UNIT_IDS: [u8; 5] = [1, 2, 3, 126, 247];
+pub const DEFAULT_SWEEP_CONCURRENCY: usize = 64;
+pub const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 500_u64;
+/// The shortest prefix a sweep accepts, so a typo can't start sweeping a /8.
+pub const MIN_PREFIX_LEN: u8 = 16;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SweepError {
+    #[error("Invalid CIDR range: {0}")]
+    InvalidCidr(String),
+    #[error("Range {0} is too large, the prefix must be at least /{MIN_PREFIX_LEN}")]
+    RangeTooLarge(String),
+}
+
+/// What to sweep for, and how hard.
+#[derive(Debug, Clone, Builder)]
+pub struct SweepOptions {
+    /// the TCP ports tried on every host
+    #[builder(default = vec![DEFAULT_MODBUS_PORT])]
+    pub ports: Vec<u16>,
+    /// the unit ids probed on every listener found
+    #[builder(default = DEFAULT_UNIT_IDS.to_vec())]
+    pub unit_ids: Vec<u8>,
+    /// how many host and port pairs are tried at once
+    #[builder(default = DEFAULT_SWEEP_CONCURRENCY)]
+    pub concurrency: usize,
+    /// how long to wait for a TCP connection
+    #[builder(default = Duration::from_millis(DEFAULT_CONNECT_TIMEOUT_MS))]
+    pub connect_timeout: Duration,
+    /// how long to wait for a unit id to answer the marker probe
+    #[builder(default = Duration::from_millis(DEFAULT_PROBE_TIMEOUT_MS))]
+    pub probe_timeout: Duration,
+    /// limits for discovering the models of each device found
+    #[builder(default)]
+    pub limits: DiscoveryLimits,
+}
+
+impl Default for SweepOptions {
+    fn default() -> Self {
+        SweepOptions::builder().build()
+    }
+}
+
+/// The SunSpec devices found behind one Modbus/TCP listener.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SweepResult {
+    pub addr: SocketAddr,
+    pub devices: Vec<ScanResult>,
+}
+
+/// List the host addresses of an IPv4 CIDR range, e.g. `192.168.1.0/24`.  The network and
+/// broadcast addresses are left out, except for /31 and /32; a bare address is a /32.
+///
+/// # Arguments
+///
+/// * `cidr` - the range, in `address/prefix` form
+pub fn cidr_hosts(cidr: &str) -> Result<Vec<Ipv4Addr>, SweepError> {
+    let invalid = || SweepError::InvalidCidr(cidr.to_string());
+    let (ip, prefix) = match cidr.trim().split_once('/') {
+        Some((ip, prefix)) => (ip, prefix.parse::<u8>().map_err(|_| invalid())?),
+        None => (cidr.trim(), 32),
+    };
+    let ip: Ipv4Addr = ip.parse().map_err(|_| invalid())?;
+    if prefix > 32 {
+        return Err(invalid());
+    }
+    if prefix < MIN_PREFIX_LEN {
+        return Err(SweepError::RangeTooLarge(cidr.to_string()));
+    }
+    let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+    let network = u32::from(ip) & mask;
+    let broadcast = network | !mask;
+    let hosts = match prefix {
+        31 | 32 => network..=broadcast,
+        _ => network + 1..=broadcast - 1,
+    };
+    Ok(hosts.map(Ipv4Addr::from).collect())
+}
+
+/// Sweep a range of addresses for Modbus/TCP listeners on any of the given ports, probe each
+/// listener's unit ids for the SunSpec marker, and discover every device found.  Results are
+/// sorted by address and only include listeners with at least one SunSpec device.
+///
+/// # Arguments
+///
+/// * `cidr` - the IPv4 range to sweep, e.g. `192.168.1.0/24`
+/// * `data` - the SunSpecData library to load model definitions from
+/// * `options` - ports, unit ids, concurrency and timeouts
+pub async fn sweep_subnet(
+    cidr: &str,
+    data: &SunSpecData,
+    options: &SweepOptions,
+) -> Result<Vec<SweepResult>, SweepError> {
+    let hosts = cidr_hosts(cidr)?;
+    let concurrency = options.concurrency.max(1);
+    // a /16 holds tens of thousands of hosts, so only start a task once one has finished
+    let mut tasks: JoinSet<Option<SweepResult>> = JoinSet::new();
+    let mut results: Vec<SweepResult> = vec![];
+    for host in hosts {
+        for port in options.ports.iter() {
+            if tasks.len() >= concurrency {
+                collect_result(tasks.join_next().await, &mut results);
+            }
+            let addr = SocketAddr::new(host.into(), *port);
+            let data = data.clone();
+            let options = options.clone();
+            tasks.spawn(async move { sweep_listener(addr, &data, &options).await });
+        }
+    }
+    while let Some(joined) = tasks.join_next().await {
+        collect_result(Some(joined), &mut results);
+    }
+    results.sort_by_key(|r| r.addr);
+    Ok(results)
+}
+
+/// Add a finished sweep task's result, if it found anything.
+fn collect_result(
+    joined: Option<Result<Option<SweepResult>, JoinError>>,
+    results: &mut Vec<SweepResult>,
+) {
+    match joined {
+        Some(Ok(Some(result))) => results.push(result),
+        Some(Ok(None)) | None => {}
+        Some(Err(e)) => warn!("sweep task failed: {e}"),
+    }
+}
+
+/// Connect to one address and scan its unit ids, if anything is listening there.
+async fn sweep_listener(
+    addr: SocketAddr,
+    data: &SunSpecData,
+    options: &SweepOptions,
+) -> Option<SweepResult> {
+    let conn = match timeout(
+        options.connect_timeout,
+        SunSpecConnection::new(addr.to_string(), None, false, None),
+    )
+    .await
+    {
+        Ok(Ok(conn)) => conn,
+        Ok(Err(e)) => {
+            trace!("[{addr}] {e}");
+            return None;
+        }
+        Err(_) => {
+            trace!("[{addr}] connection timed out");
+            return None;
+        }
+    };
+    debug!("[{addr}] is listening, probing unit ids");
+    let scan = ScanOptions::builder()
+        .concurrency(options.unit_ids.len())
+        .probe_timeout(options.probe_timeout)
+        .limits(options.limits.clone())
+        .build();
+    let devices = conn
+        .scan_slave_ids(data, options.unit_ids.iter().copied(), &scan)
+        .await;
+    if devices.is_empty() {
+        return None;
+    }
+    Some(SweepResult { addr, devices })
+}
+
+#[test]
+fn test_cidr_hosts() {
+    let hosts = cidr_hosts("192.168.1.77/30").unwrap();
+    assert_eq!(
+        hosts,
+        vec![
+            Ipv4Addr::new(192, 168, 1, 77),
+            Ipv4Addr::new(192, 168, 1, 78)
+        ]
+    );
+    assert_eq!(cidr_hosts("10.0.0.0/24").unwrap().len(), 254);
+    assert_eq!(
+        cidr_hosts("10.0.0.5").unwrap(),
+        vec![Ipv4Addr::new(10, 0, 0, 5)]
+    );
+    assert_eq!(cidr_hosts("10.0.0.4/31").unwrap().len(), 2);
+    assert_eq!(
+        cidr_hosts("10.0.0.0/8"),
+        Err(SweepError::RangeTooLarge("10.0.0.0/8".to_string()))
+    );
+    assert!(matches!(
+        cidr_hosts("10.0.0.0/33"),
+        Err(SweepError::InvalidCidr(_))
+    ));
+    assert!(matches!(
+        cidr_hosts("inverter/24"),
+        Err(SweepError::InvalidCidr(_))
+    ));
+}
@@ -1,20 +1,21 @@
-use crate::json::group::{Group, GroupCount};
+use crate::device_identity::DeviceIdentity;
+use crate::discovery::{DiscoveryError, DiscoveryLimits};
+use crate::json::group::GroupCount;
 use crate::json::point::PointType;
 use crate::metrics::{MODBUS_GET, MODBUS_SET};
 use crate::modbus_test_harness::ModbusTestHarness;
 use crate::model_data::ModelData;
 use crate::sunspec_data::SunSpecData;
 use crate::sunspec_models::{
-    Access, GroupIdentifier, LiteralType, Model, ModelSource, OptionalGroupIdentifier, Point,
-    PointIdentifier, Symbol, ValueType,
+    Access, GroupIdentifier, ModelGroup, OptionalGroupIdentifier, Point, PointIdentifier,
+    PointLiteral, ValueType,
 };
-use anyhow::{anyhow, Error};
+use crate::typed_models::SunSpecModel;
 use async_recursion::async_recursion;
 use bitvec::macros::internal::funty::Fundamental;
 use bitvec::prelude::*;
 use bon::Builder;
 use num_traits::pow::Pow;
-use num_traits::ToPrimitive;
 use pkcs8::der::Decode;
 use pkcs8::EncryptedPrivateKeyInfo;
 use rustls_pemfile::{certs, pkcs8_private_keys, private_key};
@@ -27,7 +28,7 @@ use std::io::BufReader;
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::path::Path;
 use std::string::ToString;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use thiserror::Error;
 use tokio::net::TcpStream;
@@ -41,28 +42,14 @@ use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName}
 use tokio_rustls::TlsConnector;
 
 pub const SUNSPEC_END_MODEL_ID: u16 = 65535;
-pub const POINT_TYPE_STRING: &str = "string";
-pub const POINT_TYPE_INT16: &str = "int16";
-pub const POINT_TYPE_UINT16: &str = "uint16";
-pub const POINT_TYPE_ACC16: &str = "acc16";
-pub const POINT_TYPE_ENUM16: &str = "enum16";
-pub const POINT_TYPE_BITFIELD16: &str = "bitfield16";
-pub const POINT_TYPE_INT32: &str = "int32";
-pub const POINT_TYPE_UINT32: &str = "uint32";
-pub const POINT_TYPE_INT64: &str = "int64";
-pub const POINT_TYPE_UINT64: &str = "uint64";
-pub const POINT_TYPE_ACC32: &str = "acc32";
-pub const POINT_TYPE_ACC64: &str = "acc64";
-pub const POINT_TYPE_ENUM32: &str = "enum32";
-pub const POINT_TYPE_BITFIELD32: &str = "bitfield32";
-pub const POINT_TYPE_SUNSSF: &str = "sunssf";
-pub const POINT_TYPE_PAD: &str = "pad";
 
 pub const NOT_ACCUMULATED_64: u64 = 0x0000_0000_0000_0000;
 pub const NOT_ACCUMULATED_32: u32 = 0x0000_0000;
 pub const NOT_IMPLEMENTED_U32: u32 = 0xffffffff;
 pub const NOT_IMPLEMENTED_U64: u64 = 0xffff_ffff_ffff_ffff;
 pub const NOT_IMPLEMENTED_I32: u32 = 0x80000000;
+pub const NOT_IMPLEMENTED_I64: u64 = 0x8000_0000_0000_0000;
+pub const NOT_IMPLEMENTED_EUI48: u64 = 0xffff_ffff_ffff;
 pub const NOT_ACCUMULATED_16: u16 = 0x0000;
 pub const NOT_IMPLEMENTED_U16: u16 = 0xffff;
 pub const NOT_IMPLEMENTED_I16: u16 = 0x8000;
@@ -123,7 +110,15 @@ pub enum SunSpecPointError {
     UndefinedError,
 }
 
-#[derive(Error, Debug, Default, PartialEq)]
+#[derive(Error, Debug, PartialEq)]
+pub enum SunSpecGroupError {
+    #[error("Can't resolve group count: {0}")]
+    CountUnresolved(String),
+    #[error("Not enough data for group: {0}")]
+    InsufficientData(String),
+}
+
+#[derive(Error, Debug, Default, Clone, PartialEq)]
 pub enum SunSpecReadError {
     #[error("Comm Error in read: {0}")]
     CommError(String),
@@ -157,23 +152,37 @@ pub enum SunSpecWriteError {
     Default,
 }
 
-pub trait SunSpecConn: Reader + Writer {}
+pub trait SunSpecConn: Reader + Writer {
+    /// A fresh transport to replace this one after a request timed out, if it can make one
+    /// itself, as a simulated device can; otherwise the connection is made again.
+    fn reopen(&self) -> Option<Box<dyn SunSpecConn>> {
+        None
+    }
+}
 impl SunSpecConn for Context {}
 /// A SunSpecConnection holds the address and slave id for the modbus connection, as well as the
 /// actual connection object itself as well as the modeldata for all of the exposed models on
-/// that connection.
+/// that connection.  Cloning a connection is cheap: clones share the underlying modbus context,
+/// the model map and the point catalog, so a single connection can be handed to many tasks.
 #[derive(Debug, Clone)]
 pub struct SunSpecConnection {
     /// an ip address:port pair resolved as a SocketAddr
     pub addr: SocketAddr,
     /// an optional number for modbus slave address
-    slave_num: Option<u8>,
+    pub(crate) slave_num: Option<u8>,
     /// the tokio-modbus Context object that is used for communication
     pub(crate) ctx: Arc<Mutex<Box<dyn SunSpecConn>>>,
-    /// a map of the model definitions related to this connection (populated via populate_models)
-    pub models: HashMap<u16, ModelData>,
+    /// a map of the model definitions related to this connection (populated via populate_models).
+    /// Shared between clones of this connection.
+    pub(crate) models: Arc<RwLock<HashMap<u16, ModelData>>>,
     /// a map of both the address and a retrieved value for each point, in JMES path format.
-    pub catalog: HashMap<String, PointNode>,
+    /// Shared between clones of this connection.
+    pub(crate) catalog: Arc<RwLock<HashMap<String, PointNode>>>,
+    /// who the device is, read from its common model during discovery.
+    /// Shared between clones of this connection.
+    pub(crate) identity: Arc<RwLock<Option<DeviceIdentity>>>,
+    /// the TLS settings the connection was made with, kept to make it again (see reconnect)
+    pub(crate) tls_config: Option<TlsConfig>,
     /// boolean value that causes get_point to force an error if a symbol doesn't exist.  A false
     /// value indicates that get_point can return a synthesized value instead (e.g., enum, bitfields)
     pub strict_symbol: bool,
@@ -212,12 +221,35 @@ impl SunSpecConnection {
                 anyhow::bail!("Can't resolve socket address: {socket_addr}");
             }
         };
-        let ctx: Context;
-        let slave_id = match slave_num {
-            Some(num) => Some(Slave(num)),
-            None => None,
-        };
+        let slave_id = slave_num.map(Slave);
+        let ctx = Self::connect(socket_addr, slave_id, tls_config.clone()).await?;
 
+        //let arc_ctx = Arc::new(Mutex::new(ctx));
+        Ok(SunSpecConnection {
+            addr: socket_addr,
+            slave_num,
+            ctx: Arc::new(Mutex::new(Box::new(ctx))),
+            models: Arc::new(RwLock::new(HashMap::new())),
+            catalog: Arc::new(RwLock::new(HashMap::new())),
+            identity: Arc::new(RwLock::new(None)),
+            tls_config,
+            strict_symbol,
+        })
+    }
+
+    /// Open the modbus context for a connection, over TLS if it's configured.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket_addr` - the address to connect to
+    /// * `slave_id` - the slave the context addresses by default, if any
+    /// * `tls_config` - TLS settings, or None for plain TCP
+    async fn connect(
+        socket_addr: SocketAddr,
+        slave_id: Option<Slave>,
+        tls_config: Option<TlsConfig>,
+    ) -> anyhow::Result<Context> {
+        let ctx: Context;
         match tls_config {
             Some(tls) => {
                 let mut root_cert_store = tokio_rustls::rustls::RootCertStore::empty();
@@ -281,16 +313,22 @@ impl SunSpecConnection {
                 }
             }
         }
+        Ok(ctx)
+    }
 
-        //let arc_ctx = Arc::new(Mutex::new(ctx));
-        Ok(SunSpecConnection {
-            addr: socket_addr,
-            slave_num,
-            ctx: Arc::new(Mutex::new(Box::new(ctx))),
-            models: HashMap::new(),
-            catalog: HashMap::new(),
-            strict_symbol,
-        })
+    /// Replace the modbus context shared by this connection and its clones with a fresh one to
+    /// the same address.  A request that timed out leaves its late reply on the socket, where it
+    /// would be taken for the reply to the next request; a new socket starts clean.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - the connection's context, locked by the caller
+    pub(crate) async fn reconnect(&self, ctx: &mut Box<dyn SunSpecConn>) -> anyhow::Result<()> {
+        *ctx = match ctx.reopen() {
+            Some(fresh) => fresh,
+            None => Box::new(Self::connect(self.addr, None, self.tls_config.clone()).await?),
+        };
+        Ok(())
     }
 
     pub async fn test_new(testbuf: ModbusTestHarness, strict_symbol: bool) -> anyhow::Result<Self> {
@@ -298,52 +336,51 @@ impl SunSpecConnection {
             addr: "127.0.0.1:5083".parse()?,
             slave_num: Some(0_u8),
             ctx: Arc::new(Mutex::new(Box::new(testbuf))),
-            models: HashMap::new(),
-            catalog: HashMap::new(),
+            models: Arc::new(RwLock::new(HashMap::new())),
+            catalog: Arc::new(RwLock::new(HashMap::new())),
+            identity: Arc::new(RwLock::new(None)),
+            tls_config: None,
             strict_symbol,
         })
     }
+
+    /// Return a connection to another slave behind the same socket, e.g. a gateway serving
+    /// several devices.  The modbus context is shared with this connection, and every request
+    /// is addressed to its own slave; models, catalog and identity start out empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `slave_num` - the modbus slave id of the other device
+    pub fn for_slave(&self, slave_num: u8) -> Self {
+        SunSpecConnection {
+            addr: self.addr,
+            slave_num: Some(slave_num),
+            ctx: self.ctx.clone(),
+            models: Arc::new(RwLock::new(HashMap::new())),
+            catalog: Arc::new(RwLock::new(HashMap::new())),
+            identity: Arc::new(RwLock::new(None)),
+            tls_config: self.tls_config.clone(),
+            strict_symbol: self.strict_symbol,
+        }
+    }
     //endregion
-    pub async fn get_raw(
-        &mut self,
-        addr: Address,
-        amount: u16,
-    ) -> Result<Vec<Word>, SunSpecReadError> {
-        if amount > 100 {
-            // we need to split this read into two distinct requests and then combine them
-            let amount_left = 100;
-            let address_left = addr;
-            let amount_right = amount - amount_left;
-            let address_right = addr + amount_left;
-
-            let data_left = match self
-                .retry_read_holding_registers(address_left, amount_left)
-                .await
-            {
-                Ok(d) => d,
-                Err(e) => return Err(SunSpecReadError::CommError(e.to_string())),
-            };
-            let data_right = match self
-                .retry_read_holding_registers(address_right, amount_right)
-                .await
-            {
-                Ok(d) => d,
-                Err(e) => return Err(SunSpecReadError::CommError(e.to_string())),
-            };
-            let mut combined = data_left;
-            combined.extend(data_right);
-            Ok(combined)
-        } else {
+    pub async fn get_raw(&self, addr: Address, amount: u16) -> Result<Vec<Word>, SunSpecReadError> {
+        // split large reads into requests of at most 100 registers and combine them
+        let mut combined: Vec<Word> = Vec::with_capacity(amount as usize);
+        let mut offset = 0;
+        while offset < amount {
+            let chunk = (amount - offset).min(100);
             let data = match self
-                .clone()
-                .retry_read_holding_registers(addr, amount)
+                .retry_read_holding_registers(addr + offset, chunk)
                 .await
             {
-                Ok(data) => data,
+                Ok(d) => d,
                 Err(e) => return Err(SunSpecReadError::CommError(e.to_string())),
             };
-            Ok(data)
+            combined.extend(data);
+            offset += chunk;
         }
+        Ok(combined)
     }
     //region get value primitives
     /// Get a text string from the modbus connection
@@ -354,7 +391,7 @@ impl SunSpecConnection {
     /// * `quantity` - The number of 16-bit values to read from the bus
 
     pub async fn get_string(
-        &mut self,
+        &self,
         addr: Address,
         quantity: Quantity,
     ) -> Result<String, SunSpecReadError> {
@@ -387,9 +424,9 @@ impl SunSpecConnection {
     ///
     /// * `addr` - A memory offset address to read, e.g. 40002
 
-    pub async fn get_i16(&mut self, addr: Address) -> Result<i16, SunSpecReadError> {
+    pub async fn get_i16(&self, addr: Address) -> Result<i16, SunSpecReadError> {
         let _ = MODBUS_GET.with_label_values(&["i16"]).start_timer();
-        let data = match self.clone().retry_read_holding_registers(addr, 1).await {
+        let data = match self.retry_read_holding_registers(addr, 1).await {
             Ok(data) => {
                 if data[0] == NOT_IMPLEMENTED_I16 {
                     return Err(SunSpecReadError::DatapointNotImplemented);
@@ -405,9 +442,9 @@ impl SunSpecConnection {
     /// # Arguments
     ///
     /// * `addr` - A memory offset address to read, e.g. 40002
-    pub async fn get_u16(&mut self, addr: Address) -> Result<u16, SunSpecReadError> {
+    pub async fn get_u16(&self, addr: Address) -> Result<u16, SunSpecReadError> {
         let _ = MODBUS_GET.with_label_values(&["u16"]).start_timer();
-        let data = match self.clone().retry_read_holding_registers(addr, 1).await {
+        let data = match self.retry_read_holding_registers(addr, 1).await {
             Ok(data) => {
                 if data[0] == NOT_IMPLEMENTED_U16 {
                     return Err(SunSpecReadError::DatapointNotImplemented);
@@ -425,9 +462,9 @@ impl SunSpecConnection {
     ///
     /// * `addr` - A memory offset address to read, e.g. 40002
 
-    async fn get_u16_no_check(&mut self, addr: Address) -> Result<u16, SunSpecReadError> {
+    pub(crate) async fn get_u16_no_check(&self, addr: Address) -> Result<u16, SunSpecReadError> {
         let _ = MODBUS_GET.with_label_values(&["u16_nocheck"]).start_timer();
-        let data = match self.clone().retry_read_holding_registers(addr, 1).await {
+        let data = match self.retry_read_holding_registers(addr, 1).await {
             Ok(data) => data[0],
 
             Err(e) => return Err(SunSpecReadError::CommError(e.to_string())),
@@ -440,10 +477,10 @@ impl SunSpecConnection {
     ///
     /// * `addr` - A memory offset address to read, e.g. 40002
     /// * `data` - A single 16 bit unsigned integer.
-    pub async fn set_u16(&mut self, addr: Address, data: u16) -> Result<(), SunSpecWriteError> {
+    pub async fn set_u16(&self, addr: Address, data: u16) -> Result<(), SunSpecWriteError> {
         let _ = MODBUS_SET.with_label_values(&["u16"]).start_timer();
         let word: Word = data;
-        match self.clone().retry_write_register(addr, word).await {
+        match self.retry_write_register(addr, word).await {
             Ok(_) => {}
             Err(e) => {
                 return Err(SunSpecWriteError::CommError(e.to_string()));
@@ -459,9 +496,9 @@ impl SunSpecConnection {
     ///
     /// * `addr` - A memory offset address to read, e.g. 40002
 
-    pub async fn get_i32(&mut self, addr: Address) -> Result<i32, SunSpecReadError> {
+    pub async fn get_i32(&self, addr: Address) -> Result<i32, SunSpecReadError> {
         let _ = MODBUS_GET.with_label_values(&["i32"]).start_timer();
-        match self.clone().retry_read_holding_registers(addr, 2).await {
+        match self.retry_read_holding_registers(addr, 2).await {
             // because holding_registers works in 16 bit "words", we need to combine two words into
             // one word here to get a 32 bit number.
             Ok(data) => {
@@ -485,9 +522,9 @@ impl SunSpecConnection {
     ///
     /// * `addr` - A memory offset address to read, e.g. 40002
 
-    pub async fn get_u64(&mut self, addr: Address) -> Result<u64, SunSpecReadError> {
+    pub async fn get_u64(&self, addr: Address) -> Result<u64, SunSpecReadError> {
         let _ = MODBUS_GET.with_label_values(&["u64"]).start_timer();
-        match self.clone().retry_read_holding_registers(addr, 4).await {
+        match self.retry_read_holding_registers(addr, 4).await {
             // because holding_registers works in 16 bit "words", we need to combine two words into
             // one word here to get a 32 bit number.
             Ok(data) => {
@@ -515,9 +552,9 @@ impl SunSpecConnection {
     ///
     /// * `addr` - A memory offset address to read, e.g. 40002
 
-    pub async fn get_u32(&mut self, addr: Address) -> Result<u32, SunSpecReadError> {
+    pub async fn get_u32(&self, addr: Address) -> Result<u32, SunSpecReadError> {
         let _ = MODBUS_GET.with_label_values(&["u32"]).start_timer();
-        match self.clone().retry_read_holding_registers(addr, 2).await {
+        match self.retry_read_holding_registers(addr, 2).await {
             // because holding_registers works in 16 bit "words", we need to combine two words into
             // one word here to get a 32 bit number.
             Ok(data) => {
@@ -537,7 +574,7 @@ impl SunSpecConnection {
     //region inner writing register retry logic
 
     pub(crate) async fn retry_write_register(
-        self,
+        &self,
         addr: Address,
         data: Word,
     ) -> Result<(), SunSpecCommError> {
@@ -545,10 +582,9 @@ impl SunSpecConnection {
             .map(jitter) // add jitter to delays
             .take(3); // limit to 3 retries
 
-        let ctx = self.ctx.clone();
         match RetryIf::spawn(
             retry_strategy,
-            || action_write_register(&ctx, addr, data),
+            || action_write_register(self, addr, data),
             |e: &SunSpecCommError| SunSpecCommError::TransientError == *e,
         )
         .await
@@ -564,7 +600,7 @@ impl SunSpecConnection {
     //region inner holding registers retry logic
 
     pub(crate) async fn retry_read_holding_registers(
-        &mut self,
+        &self,
         addr: Address,
         q: Quantity,
     ) -> Result<Vec<Word>, SunSpecCommError> {
@@ -572,13 +608,9 @@ impl SunSpecConnection {
             .map(jitter) // add jitter to delays
             .take(3); // limit to 3 retries
 
-        let ctx = self.ctx.clone();
         match RetryIf::spawn(
             retry_strategy,
-            || {
-                let future = action_read_holding_registers(&ctx, addr, q);
-                future
-            },
+            || action_read_holding_registers(self, addr, q),
             |e: &SunSpecCommError| SunSpecCommError::TransientError == *e,
         )
         .await
@@ -592,80 +624,155 @@ impl SunSpecConnection {
     //endregion
 
     //region gather models from the device and store them
-
+    /// Walk the device's model chain, load a definition for each model found and build the
+    /// point catalog.  The result is stored on the connection (and so shared with its clones)
+    /// as well as returned.  This fails if no model at all could be discovered, or if a model's
+    /// point catalog is incomplete, e.g. because a group count couldn't be resolved; the models
+    /// found are stored even then.  Other problems with individual models are only logged; see
+    /// discover_models for them.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - the SunSpecData library to load model definitions from
     pub async fn populate_models(
-        &mut self,
+        &self,
         data: &SunSpecData,
     ) -> anyhow::Result<HashMap<u16, ModelData>> {
-        let mut address = 40002;
-        let mut models: HashMap<u16, ModelData> = HashMap::new();
-        let manufacturer = match self.get_string(address + 2, 16).await {
-            Ok(s) => match s.trim_matches(char::from(0)).parse() {
-                Ok(s) => Some(s),
-                Err(e) => {
-                    warn!("Can't trim nulls on manufacturer name: {e}");
-                    None
-                }
-            },
-            Err(e) => {
-                info!(
-                    "[{}:{}] Can't get manufacturer of this unit: {e}",
-                    self.addr,
-                    self.slave_num.unwrap()
-                );
-                None
+        let report = self
+            .discover_models(data, &DiscoveryLimits::default())
+            .await;
+        for e in report.errors.iter() {
+            warn!("[{}] model discovery: {e}", self.addr);
+        }
+        if report.models.is_empty() && !report.complete {
+            match report.errors.last() {
+                Some(e) => anyhow::bail!("Model discovery failed: {e}"),
+                None => anyhow::bail!("Model discovery failed"),
             }
-        };
-        loop {
-            let id = match self.get_u16_no_check(address).await {
-                Ok(id) => id,
-                Err(e) => {
-                    anyhow::bail!("Can't get model id: {e}");
-                }
-            };
-            let length = match self.get_u16(address + 1).await {
-                Ok(length) => length,
-                Err(e) => {
-                    anyhow::bail!("Can't get model length: {e}");
-                }
-            };
-            if id == SUNSPEC_END_MODEL_ID {
-                break;
+        }
+        // catalog paths into the model would silently be missing
+        if let Some(e) = report
+            .errors
+            .iter()
+            .find(|e| matches!(e, DiscoveryError::Catalog(..)))
+        {
+            anyhow::bail!("Model discovery failed: {e}");
+        }
+        Ok(report.models)
+    }
+
+    /// Returns the model data for a populated model.  ModelData is cheap to clone, and clones
+    /// share their scale factor cache.
+    pub fn model(&self, id: u16) -> Option<ModelData> {
+        self.models.read().unwrap().get(&id).cloned()
+    }
+
+    /// Returns a snapshot of every populated model, keyed by model id.
+    pub fn models(&self) -> HashMap<u16, ModelData> {
+        self.models.read().unwrap().clone()
+    }
+
+    /// Returns a copy of a single entry from the point catalog.
+    pub fn catalog_entry(&self, path: &str) -> Option<PointNode> {
+        self.catalog.read().unwrap().get(path).cloned()
+    }
+
+    /// Returns a snapshot of the whole point catalog.
+    pub fn catalog(&self) -> HashMap<String, PointNode> {
+        self.catalog.read().unwrap().clone()
+    }
+
+    /// Read a whole model in one pass and decode it into a typed struct, such as the ones
+    /// generated by the `typed-models` feature.
+    ///
+    /// ```ignore
+    /// let inverter: Model103 = conn.read_model().await?;
+    /// ```
+    pub async fn read_model<T: SunSpecModel>(&self) -> Result<T, SunSpecReadError> {
+        let data = self.read_model_raw(T::ID).await?;
+        T::decode(&data).map_err(|e| SunSpecReadError::OtherError(e.to_string()))
+    }
+
+    /// Read a populated model's whole register block, starting at its ID register.  This works
+    /// for every model the device exposes, including ones without a definition.
+    pub async fn read_model_raw(&self, model_id: u16) -> Result<Vec<Word>, SunSpecReadError> {
+        let md = match self.model(model_id) {
+            Some(md) => md,
+            None => {
+                return Err(SunSpecReadError::OtherError(format!(
+                    "model {model_id} is not present on this device"
+                )))
             }
-            assert!(id >= 1);
-            info!("found model with id {id}, and length {length}");
-            match ModelData::new(
-                data.clone(),
-                id as u16,
-                length,
-                address,
-                manufacturer.clone(),
-            )
-            .await
-            {
-                Ok(md) => {
-                    // if this is a json model, populate group catalog
-                    if let ModelSource::Json(json) = md.clone().model.source {
-                        if let Ok(mut data) = self.get_raw(md.address + 2, md.len).await {
-                            process_json_group(
-                                &mut data,
-                                &json.group,
-                                None,
-                                &mut md.address.clone(),
-                                &mut self.catalog,
-                            )
-                            .await;
-                        }
+        };
+        self.get_raw(md.address, md.len + ADDR_OFFSET).await
+    }
+    //endregion
+    //region resolve point address
+    /// Locate a point inside a model and compute the absolute register address it lives at,
+    /// without reading anything from the device.
+    ///
+    /// # Arguments
+    ///
+    /// * `md` - A ModelData instance that has been initialized for the model being queried.
+    /// * `point_identifier` - Either a catalog path or a point name.
+    pub fn resolve_point(
+        &self,
+        md: &ModelData,
+        point_identifier: &PointIdentifier,
+    ) -> Result<(Point, Address), SunSpecPointError> {
+        match point_identifier {
+            PointIdentifier::Catalog(catalog_name) => match self.catalog_entry(catalog_name) {
+                Some(pn) => Ok((pn.point_data, pn.address)),
+                None => Err(SunSpecPointError::DoesNotExist(format!(
+                    "catalog entry {catalog_name} not found"
+                ))),
+            },
+            PointIdentifier::Point(point_str) => match md.model.group.locate(point_str) {
+                Some((p, offset)) => Ok((p.clone(), md.address + offset)),
+                None => Err(SunSpecPointError::DoesNotExist(format!(
+                    "{}/{point_str}",
+                    md.model.model.name
+                ))),
+            },
+            PointIdentifier::Repeating(instance, point_str) => {
+                let (fixed_len, group) = match md.repeating_group() {
+                    Some(g) => g,
+                    None => {
+                        return Err(SunSpecPointError::DoesNotExist(format!(
+                            "{} has no repeating block",
+                            md.model.model.name
+                        )));
                     }
-                    models.insert(id as u16, md);
+                };
+                let count = match md.get_block_count() {
+                    Ok(c) => c,
+                    Err(e) => {
+                        return Err(SunSpecPointError::GeneralError(format!(
+                            "{}: {e}",
+                            md.model.model.name
+                        )));
+                    }
+                };
+                if *instance == 0 || *instance > count {
+                    return Err(SunSpecPointError::DoesNotExist(format!(
+                        "{}/{point_identifier}: model has {count} repeats",
+                        md.model.model.name
+                    )));
                 }
-                Err(e) => {
-                    warn!("Couldn't create ModelData: {e}");
+                // get_block_count has already checked the group has a fixed length
+                let group_len = group.instance_len().unwrap_or(0);
+                match group.locate(point_str) {
+                    Some((p, offset)) => {
+                        let addr = md.address + fixed_len + (instance - 1) * group_len + offset;
+                        Ok((p.clone(), addr))
+                    }
+                    None => Err(SunSpecPointError::DoesNotExist(format!(
+                        "{}/{point_identifier}",
+                        md.model.model.name
+                    ))),
                 }
-            };
-            address = address + length + ADDR_OFFSET;
+            }
         }
-        Ok(models)
     }
     //endregion
     //region set point value
@@ -685,37 +792,16 @@ impl SunSpecConnection {
     #[async_recursion]
 
     pub async fn set_point(
-        mut self,
-        md: ModelData,
+        &self,
+        md: &ModelData,
         point_identifier: PointIdentifier,
         data: ValueType,
     ) -> Result<(), SunSpecWriteError> {
-        let mut point = Point::default();
-        let model = md.model.model.clone();
-        let mut catalog_entry: Option<PointNode> = None;
-        let mut name = String::new();
-
-        match point_identifier {
-            PointIdentifier::Catalog(catalog_name) => {
-                info!("Catalog name: {catalog_name} specified.  Will use json-supplied point data");
-                catalog_entry = self.catalog.get(&catalog_name).cloned();
-                point = catalog_entry.clone().unwrap().point_data;
-            }
-            PointIdentifier::Point(point_str) => {
-                name = point_str.clone();
-                model.block.iter().for_each(|b| {
-                    b.point.iter().for_each(|p| {
-                        if p.id == name {
-                            point = p.clone();
-                        }
-                    })
-                });
-            }
+        let name = point_identifier.to_string();
+        let (point, write_addr) = match self.resolve_point(md, &point_identifier) {
+            Ok(resolved) => resolved,
+            Err(_) => return Err(SunSpecWriteError::PointDoesntExist),
         };
-
-        if point.id.len() == 0 {
-            return Err(SunSpecWriteError::PointDoesntExist);
-        }
         match point.access {
             None => {
                 warn!("Can't determine if this point {name} is writeable, assuming read-only.");
@@ -730,12 +816,8 @@ impl SunSpecConnection {
                 }
             },
         };
-        let write_addr = match catalog_entry {
-            Some(pn) => pn.address,
-            None => ADDR_OFFSET + md.address + point.offset,
-        };
-        match point.r#type.as_str() {
-            POINT_TYPE_UINT16 | POINT_TYPE_ENUM16 | POINT_TYPE_BITFIELD16 => {
+        match point.r#type {
+            PointType::Uint16 | PointType::Enum16 | PointType::Bitfield16 => {
                 if let ValueType::Integer(val) = data {
                     if val < 0 {
                         return Err(SunSpecWriteError::ValueDoesntMatchPoint);
@@ -751,11 +833,11 @@ impl SunSpecConnection {
                         }
                     }
                 } else {
-                    error!("Point type {POINT_TYPE_ENUM16} requires an integer to set.");
+                    error!("Point type {:?} requires an integer to set.", point.r#type);
                     return Err(SunSpecWriteError::ValueDoesntMatchPoint);
                 }
             }
-            POINT_TYPE_UINT32 | POINT_TYPE_ENUM32 | POINT_TYPE_BITFIELD32 => {
+            PointType::Uint32 | PointType::Enum32 | PointType::Bitfield32 => {
                 if let ValueType::Integer(val) = data {
                     if val < 0 {
                         return Err(SunSpecWriteError::ValueDoesntMatchPoint);
@@ -773,11 +855,11 @@ impl SunSpecConnection {
                         }
                     }
                 } else {
-                    error!("Point type {POINT_TYPE_ENUM16} requires an integer to set.");
+                    error!("Point type {:?} requires an integer to set.", point.r#type);
                     return Err(SunSpecWriteError::ValueDoesntMatchPoint);
                 }
             }
-            POINT_TYPE_UINT64 => {
+            PointType::Uint64 => {
                 if let ValueType::Integer(val) = data {
                     if val < 0 {
                         return Err(SunSpecWriteError::ValueDoesntMatchPoint);
@@ -795,11 +877,11 @@ impl SunSpecConnection {
                         }
                     }
                 } else {
-                    error!("Point type {POINT_TYPE_ENUM16} requires an integer to set.");
+                    error!("Point type {:?} requires an integer to set.", point.r#type);
                     return Err(SunSpecWriteError::ValueDoesntMatchPoint);
                 }
             }
-            &_ => {
+            _ => {
                 error!("Unimplemented write type.");
                 return Err(SunSpecWriteError::Default);
             }
@@ -819,466 +901,74 @@ impl SunSpecConnection {
     ///            values specified in the sunspec model files.
     #[async_recursion]
     pub async fn get_point(
-        mut self,
-        mut md: ModelData,
+        &self,
+        md: &ModelData,
         point_identifier: PointIdentifier,
     ) -> Result<Point, SunSpecPointError> {
-        let mut point = Point::default();
-        let mut symbols: Option<Vec<Symbol>> = None;
-        let model = md.model.model.clone();
-        let model_name = model.clone().name;
-
-        let mut catalog_entry: Option<PointNode> = None;
-        let mut point_name: String = String::new();
-        match point_identifier.clone() {
-            PointIdentifier::Catalog(catalog_name) => {
-                info!("Catalog name: {catalog_name} specified.  Will use json-supplied point data");
-                catalog_entry = self.catalog.get(&catalog_name).cloned();
-                if catalog_entry.is_some() {
-                    point = catalog_entry.clone().unwrap().point_data;
-                    symbols = point.symbol.clone();
-                } else {
-                    trace!("Requested {catalog_name}, not found. Iterating through available catalog points:");
-                    for (c, _) in self.catalog.iter() {
-                        trace!("{c}");
-                    }
-                    info!("Catalog entry for {catalog_name} not found.  Using default point data.");
-                }
-            }
-            PointIdentifier::Point(point_str) => {
-                point_name = point_str.clone();
-                model.block.iter().enumerate().for_each(|(idx, b)| {
-                    b.point.iter().for_each(|p| {
-                        if p.id == point_str {
-                            point = p.clone();
-                            // if this point also has associated symbols (enum/bitfield), copy them in too
-                            if p.symbol.is_some() {
-                                symbols = p.symbol.clone();
-                            }
-                            return;
-                        }
-                    })
-                });
+        let model_name = md.model.model.name.clone();
+        let (mut point, read_addr) = match self.resolve_point(md, &point_identifier) {
+            Ok(resolved) => resolved,
+            Err(_) => {
+                let err = format!(
+                    "You asked for point {model_name}/{point_identifier} but it doesn't exist in the specified block."
+                );
+                return Err(SunSpecPointError::DoesNotExist(err));
             }
         };
-
-        if point.id.len() == 0 {
-            let err = format!(
-                "You asked for point {model_name}/{point_identifier} but it doesn't exist in the specified block."
-            );
-            return Err(SunSpecPointError::DoesNotExist(err));
-        }
+        let point_name = point.id.clone();
+        // scale factors that live in a repeating block come from the same repeat instance
+        let sf_block = match point_identifier {
+            PointIdentifier::Repeating(n, _) => Some(GroupIdentifier::Integer(n)),
+            _ => None,
+        };
         //region if there's literals for this point, populate them
-        if catalog_entry.is_none() {
-            for string in md.model.strings.iter() {
-                for literal in string.literals.iter() {
-                    if let LiteralType::Point(point_literal) = literal {
-                        if point_literal.id == point_name {
-                            point.literal = Some(point_literal.clone());
-                        }
-                    }
-                }
+        if !matches!(point_identifier, PointIdentifier::Catalog(_)) {
+            if let Some(text) = md.model.point_text(&point_name, None) {
+                point.literal = Some(PointLiteral {
+                    id: point_name.clone(),
+                    label: text.label,
+                    description: text.description,
+                    notes: text.notes,
+                });
             }
         }
         //endregion
-        // TODO: Here's where I'd read catalog data for a static point
-        let read_addr = match catalog_entry {
-            Some(pn) => pn.address,
-            None => ADDR_OFFSET + md.address + point.offset,
-        };
 
-        match point.r#type.as_str() {
-            POINT_TYPE_STRING => {
-                match self.get_string(read_addr, point.len.unwrap()).await {
-                    Ok(rs) => {
-                        debug!("{model_name}/{point_name} is {rs}!");
-                        let mut val = rs.clone();
-                        // it is unlikely anyone wants the extra nulls at the end of the string
-                        val = match val.trim_matches(char::from(0)).parse() {
-                            Ok(v) => v,
-                            Err(e) => {
-                                return Err(SunSpecPointError::GeneralError(format!(
-                                    "Failure trimming string nulls: {e}"
-                                )));
-                            }
-                        };
-                        point.value = Some(ValueType::String(val));
-                        return Ok(point);
-                    }
-                    Err(e) => {
-                        let err = format!(
-                            "{}:{} -- {model_name}/{point_name}: {e}",
-                            self.addr,
-                            self.slave_num.unwrap_or(0)
-                        );
-                        debug!(err);
-                        if let SunSpecReadError::CommError(_) = e {
-                            return Err(SunSpecPointError::CommError(err));
-                        } else {
-                            return Err(SunSpecPointError::GeneralError(err));
-                        }
-                    }
-                };
+        if point.r#type == PointType::Pad {
+            point.value = Some(ValueType::Pad);
+            return Ok(point);
+        }
+        let point_error = |e: SunSpecReadError| {
+            let err = format!(
+                "{}:{} -- {model_name}/{point_name}: {e}",
+                self.addr,
+                self.slave_num.unwrap_or(0)
+            );
+            debug!(err);
+            if let SunSpecReadError::CommError(_) = e {
+                SunSpecPointError::CommError(err)
+            } else {
+                SunSpecPointError::GeneralError(err)
             }
-            POINT_TYPE_INT16 => match self.get_i16(read_addr).await {
-                Ok(rs) => {
-                    debug!("{model_name}/{point_name} is {rs}!");
-                    if let Some(sf_name) = point.clone().scale_factor {
-                        if let Some(sf) = md
-                            .get_scale_factor(&sf_name, self.clone(), None, None)
-                            .await
-                        {
-                            point.value = Some(ValueType::Float(apply_scale_factor(rs, sf)));
-                            return Ok(point);
-                        }
-                    }
-                    point.value = Some(ValueType::Integer(rs as i64));
-                    return Ok(point);
-                }
-                Err(e) => {
-                    let err = format!(
-                        "{}:{} -- {model_name}/{point_name}: {e}",
-                        self.addr,
-                        self.slave_num.unwrap_or(0)
-                    );
-                    debug!(err);
-                    if let SunSpecReadError::CommError(_) = e {
-                        return Err(SunSpecPointError::CommError(err));
-                    } else {
-                        return Err(SunSpecPointError::GeneralError(err));
-                    }
-                }
-            },
-            POINT_TYPE_UINT16 | POINT_TYPE_ACC16 => match self.get_u16(read_addr).await {
-                Ok(rs) => {
-                    debug!("{model_name}/{point_name} is {rs}!");
-                    if point.r#type.as_str() == POINT_TYPE_ACC16 && rs == NOT_ACCUMULATED_16 {
-                        let err = format!(
-                            "Accumulator datapoint not supported by device (0 value returned)"
-                        );
-                        debug!(err);
-                        return Err(SunSpecPointError::GeneralError(err));
-                    }
-                    if let Some(sf_name) = point.clone().scale_factor {
-                        if let Some(sf) = md
-                            .get_scale_factor(&sf_name, self.clone(), None, None)
-                            .await
-                        {
-                            point.value = Some(ValueType::Float(apply_scale_factor(rs, sf)));
-                            return Ok(point);
-                        }
-                    }
-                    point.value = Some(ValueType::Integer(rs as i64));
-                    return Ok(point);
-                }
-                Err(e) => {
-                    let err = format!(
-                        "{}:{} -- {model_name}/{point_name}: {e}",
-                        self.addr,
-                        self.slave_num.unwrap_or(0)
-                    );
-                    debug!(err);
-                    if let SunSpecReadError::CommError(_) = e {
-                        return Err(SunSpecPointError::CommError(err));
-                    } else {
-                        return Err(SunSpecPointError::GeneralError(err));
-                    }
-                }
-            },
-            POINT_TYPE_ENUM16 => match self.get_u16(read_addr).await {
-                Ok(rs) => {
-                    debug!("{model_name}/{point_name} is {rs}!");
-                    if symbols.is_some() {
-                        let mut symbol_name: String = "".to_string();
-                        symbols.unwrap().iter().for_each(|s| {
-                            if s.symbol.parse::<u16>().unwrap() == rs {
-                                symbol_name = s.id.clone();
-                            }
-                        });
-                        if symbol_name.len() > 0 {
-                            point.value = Some(ValueType::String(symbol_name));
-                            return Ok(point);
-                        } else {
-                            if self.strict_symbol {
-                                return Err(SunSpecPointError::GeneralError(
-                                    format!("Enum failure: text symbol doesn't exist for point numeric value (point is {rs})"))
-                                );
-                            } else {
-                                point.value = Some(ValueType::String(format!("ENUM16_{rs}")));
-                                return Ok(point);
-                            }
-                        }
-                    } else {
-                        return Err(SunSpecPointError::GeneralError(
-                        format!("An enum was queried but no symbols are present for the point, so can't render.")));
-                    }
-                }
-                Err(e) => {
-                    let err = format!(
-                        "{}:{} -- {model_name}/{point_name}: {e}",
-                        self.addr,
-                        self.slave_num.unwrap_or(0)
-                    );
-                    debug!(err);
-                    if let SunSpecReadError::CommError(_) = e {
-                        return Err(SunSpecPointError::CommError(err));
-                    } else {
-                        return Err(SunSpecPointError::GeneralError(err));
-                    }
-                }
-            },
-            POINT_TYPE_BITFIELD16 => match self.get_u16(read_addr).await {
-                Ok(rs) => {
-                    debug!("{model_name}/{point_name} is {rs}!");
-                    if symbols.is_some() {
-                        let mut values: Vec<String> = vec![];
-                        let bv = BitVec::<_, Lsb0>::from_element(rs.clone());
-                        for s in symbols.unwrap().iter() {
-                            if bv[s.symbol.parse::<usize>().unwrap()] {
-                                values.push(s.id.clone());
-                            };
-                        }
-                        point.value = Some(ValueType::Array(values));
-                        return Ok(point);
-                    } else {
-                        return if self.strict_symbol {
-                            let err = format!("We tried to parse a bitfield but there aren't symbols for this point.");
-                            Err(SunSpecPointError::GeneralError(err))
-                        } else {
-                            point.value = Some(ValueType::String(format!("BITFIELD16_{rs}")));
-                            Ok(point)
-                        };
-                    }
-                }
-                Err(e) => {
-                    let err = format!(
-                        "{}:{} -- {model_name}/{point_name}: {e}",
-                        self.addr,
-                        self.slave_num.unwrap_or(0)
-                    );
-                    debug!(err);
-                    if let SunSpecReadError::CommError(_) = e {
-                        return Err(SunSpecPointError::CommError(err));
-                    } else {
-                        return Err(SunSpecPointError::GeneralError(err));
-                    }
-                }
-            },
-            POINT_TYPE_SUNSSF => match self.get_i16(read_addr).await {
-                Ok(rs) => {
-                    debug!("{model_name}/{point_name} is {rs}!");
-                    point.value = Some(ValueType::Integer(rs as i64));
-                    return Ok(point);
-                }
-                Err(e) => {
-                    let err = format!(
-                        "{}:{} -- {model_name}/{point_name}: {e}",
-                        self.addr,
-                        self.slave_num.unwrap_or(0)
-                    );
-                    debug!(err);
-                    if let SunSpecReadError::CommError(_) = e {
-                        return Err(SunSpecPointError::CommError(err));
-                    } else {
-                        return Err(SunSpecPointError::GeneralError(err));
-                    }
-                }
-            },
-            POINT_TYPE_UINT32 | POINT_TYPE_ACC32 => match self.get_u32(read_addr).await {
-                Ok(rs) => {
-                    debug!("{model_name}/{point_name} is {rs}!");
-                    if point.r#type.as_str() == POINT_TYPE_ACC32 && rs == NOT_ACCUMULATED_32 {
-                        let err = format!(
-                            "Accumulator datapoint not supported by device (0 value returned)"
-                        );
-                        debug!(err);
-                        return Err(SunSpecPointError::GeneralError(err));
-                    }
-                    if let Some(sf_name) = point.clone().scale_factor {
-                        if let Some(sf) = md
-                            .get_scale_factor(&sf_name, self.clone(), None, None)
-                            .await
-                        {
-                            point.value = Some(ValueType::Float(apply_scale_factor(rs, sf)));
-                            return Ok(point);
-                        }
-                    }
-                    point.value = Some(ValueType::Integer(rs as i64));
-                    return Ok(point);
-                }
-                Err(e) => {
-                    let err = format!(
-                        "{}:{} -- {model_name}/{point_name}: {e}",
-                        self.addr,
-                        self.slave_num.unwrap_or(0)
-                    );
-                    debug!(err);
-                    if let SunSpecReadError::CommError(_) = e {
-                        return Err(SunSpecPointError::CommError(err));
-                    } else {
-                        return Err(SunSpecPointError::GeneralError(err));
-                    }
-                }
-            },
-            POINT_TYPE_UINT64 | POINT_TYPE_ACC64 => match self.get_u64(read_addr).await {
-                Ok(rs) => {
-                    debug!("{model_name}/{point_name} is {rs}!");
-                    if point.r#type.as_str() == POINT_TYPE_ACC64 && rs == NOT_ACCUMULATED_64 {
-                        let err = format!(
-                            "Accumulator datapoint not supported by device (0 value returned)"
-                        );
-                        debug!(err);
-                        return Err(SunSpecPointError::GeneralError(err));
-                    }
-                    if let Some(sf_name) = point.clone().scale_factor {
-                        if let Some(sf) = md
-                            .get_scale_factor(&sf_name, self.clone(), None, None)
-                            .await
-                        {
-                            point.value = Some(ValueType::Float(apply_scale_factor(rs as f64, sf)));
-                            return Ok(point);
-                        }
-                    }
-                    point.value = Some(ValueType::Integer(rs as i64));
-                    return Ok(point);
-                }
-                Err(e) => {
-                    let err = format!(
-                        "{}:{} -- {model_name}/{point_name}: {e}",
-                        self.addr,
-                        self.slave_num.unwrap_or(0)
-                    );
-                    debug!(err);
-                    if let SunSpecReadError::CommError(_) = e {
-                        return Err(SunSpecPointError::CommError(err));
-                    } else {
-                        return Err(SunSpecPointError::GeneralError(err));
-                    }
-                }
-            },
-            POINT_TYPE_INT32 => match self.get_i32(read_addr).await {
-                Ok(rs) => {
-                    debug!("{model_name}/{point_name} is {rs}!");
-                    if let Some(sf_name) = point.clone().scale_factor {
-                        if let Some(sf) = md
-                            .get_scale_factor(&sf_name, self.clone(), None, None)
-                            .await
-                        {
-                            point.value = Some(ValueType::Float(apply_scale_factor(rs, sf)));
-                            return Ok(point);
-                        }
-                    }
-                    point.value = Some(ValueType::Integer(rs as i64));
-                    return Ok(point);
-                }
-                Err(e) => {
-                    let err = format!(
-                        "{}:{} -- {model_name}/{point_name}: {e}",
-                        self.addr,
-                        self.slave_num.unwrap_or(0)
-                    );
-                    debug!(err);
-                    if let SunSpecReadError::CommError(_) = e {
-                        return Err(SunSpecPointError::CommError(err));
-                    } else {
-                        return Err(SunSpecPointError::GeneralError(err));
-                    }
-                }
-            },
-            POINT_TYPE_ENUM32 => match self.get_u32(read_addr).await {
-                Ok(rs) => {
-                    debug!("{model_name}/{point_name} is {rs}!");
-                    if symbols.is_some() {
-                        let mut symbol_name: String = "".to_string();
-                        symbols.unwrap().iter().for_each(|s| {
-                            if s.symbol.parse::<u32>().unwrap() == rs {
-                                symbol_name = s.id.clone();
-                            }
-                        });
-                        if symbol_name.len() > 0 {
-                            point.value = Some(ValueType::String(symbol_name));
-                            return Ok(point);
-                        } else {
-                            if self.strict_symbol {
-                                return Err(SunSpecPointError::GeneralError(
-                                        format!("Enum failure: text symbol doesn't exist for point numeric value (point is {rs})"))
-                                    );
-                            } else {
-                                point.value = Some(ValueType::String(format!("ENUM32_{rs}")));
-                                return Ok(point);
-                            }
-                        }
-                    } else {
-                        return Err(SunSpecPointError::GeneralError(
-                format!("An enum was queried but no symbols are present for the point, so can't render.")));
-                    }
-                }
-                Err(e) => {
-                    let err = format!(
-                        "{}:{} -- {model_name}/{point_name}: {e}",
-                        self.addr,
-                        self.slave_num.unwrap_or(0)
-                    );
-                    debug!(err);
-                    if let SunSpecReadError::CommError(_) = e {
-                        return Err(SunSpecPointError::CommError(err));
-                    } else {
-                        return Err(SunSpecPointError::GeneralError(err));
-                    }
-                }
-            },
-            POINT_TYPE_BITFIELD32 => match self.get_u32(read_addr).await {
-                Ok(rs) => {
-                    debug!("{model_name}/{point_name} is {rs}!");
-                    if symbols.is_some() {
-                        let mut values: Vec<String> = vec![];
-                        let bv = BitVec::<_, Lsb0>::from_element(rs.clone());
-                        for s in symbols.unwrap().iter() {
-                            if bv[s.symbol.parse::<usize>().unwrap()] {
-                                values.push(s.id.clone());
-                            };
-                        }
-                        point.value = Some(ValueType::Array(values));
-                        return Ok(point);
-                    } else {
-                        return if self.strict_symbol {
-                            let err = format!("We tried to parse a bitfield but there aren't symbols for this point.");
-                            Err(SunSpecPointError::GeneralError(err))
-                        } else {
-                            point.value = Some(ValueType::String(format!("BITFIELD32_{rs}")));
-                            Ok(point)
-                        };
-                    }
-                }
-                Err(e) => {
-                    let err = format!(
-                        "{}:{} -- {model_name}/{point_name}: {e}",
-                        self.addr,
-                        self.slave_num.unwrap_or(0)
-                    );
-                    debug!(err);
-                    if let SunSpecReadError::CommError(_) = e {
-                        return Err(SunSpecPointError::CommError(err));
-                    } else {
-                        return Err(SunSpecPointError::GeneralError(err));
-                    }
+        };
+        let Some(len) = point_len(&point) else {
+            let err = format!("{model_name}/{point_name}: point has no length");
+            debug!(err);
+            return Err(SunSpecPointError::DoesNotExist(err));
+        };
+        let data = self.get_raw(read_addr, len).await.map_err(point_error)?;
+        let mut value =
+            decode_point_value(&point, &data, self.strict_symbol).map_err(point_error)?;
+        debug!("{model_name}/{point_name} is {value:?}!");
+        if point.r#type != PointType::Sunssf {
+            if let Some(sf_name) = point.scale_factor.clone() {
+                if let Some(sf) = md.get_scale_factor(&sf_name, self, sf_block, None).await {
+                    value = scale_value(value, sf);
                 }
-            },
-            POINT_TYPE_PAD => {
-                point.value = Some(ValueType::Pad);
-                return Ok(point);
-            }
-            _ => {
-                let err = format!(
-                    "{model_name}/{point_name}: unknown point type: {:#?}",
-                    point.r#type.as_str()
-                );
-                debug!(err);
-                return Err(SunSpecPointError::DoesNotExist(err));
             }
         }
-
-        //Err(SunSpecPointError::UndefinedError)
+        point.value = Some(value);
+        Ok(point)
     }
     //endregion
 }
@@ -1286,11 +976,14 @@ impl SunSpecConnection {
 //region actual code that reads holding registers (for retry logic)
 
 pub(crate) async fn action_read_holding_registers(
-    actx: &Arc<Mutex<Box<dyn SunSpecConn>>>,
+    conn: &SunSpecConnection,
     addr: Address,
     q: Quantity,
 ) -> Result<Vec<Word>, SunSpecCommError> {
-    let mut ctx = actx.lock().await;
+    let mut ctx = conn.ctx.lock().await;
+    // connections for different slaves may share one socket, so address every request, even
+    // one without a slave id of its own
+    ctx.set_slave(conn.slave_num.map_or(Slave::tcp_device(), Slave));
     match timeout(
         Duration::from_millis(DEFAULT_NETWORK_TIMEOUT_MS),
         ctx.read_holding_registers(addr, q),
@@ -1335,6 +1028,13 @@ pub(crate) async fn action_read_holding_registers(
         },
         Err(e) => {
             warn!("Timeout attempting read: {e}");
+            // the late reply would answer the next request on the socket, whichever slave it's for
+            if let Err(e) = conn.reconnect(&mut ctx).await {
+                warn!(
+                    "[{}] can't reconnect after a read timed out: {e}",
+                    conn.addr
+                );
+            }
             return Err(SunSpecCommError::TransientError);
         }
     }
@@ -1344,11 +1044,14 @@ pub(crate) async fn action_read_holding_registers(
 //region actual code that writes a single register
 
 pub(crate) async fn action_write_register(
-    actx: &Arc<Mutex<Box<dyn SunSpecConn>>>,
+    conn: &SunSpecConnection,
     addr: Address,
     data: Word,
 ) -> Result<(), SunSpecCommError> {
-    let mut ctx = actx.lock().await;
+    let mut ctx = conn.ctx.lock().await;
+    // connections for different slaves may share one socket, so address every request, even
+    // one without a slave id of its own
+    ctx.set_slave(conn.slave_num.map_or(Slave::tcp_device(), Slave));
     match timeout(
         Duration::from_millis(DEFAULT_NETWORK_TIMEOUT_MS),
         ctx.write_single_register(addr, data),
@@ -1376,6 +1079,13 @@ pub(crate) async fn action_write_register(
         },
         Err(e) => {
             warn!("Request timed out, retrying: {e}");
+            // the late reply would answer the next request on the socket, whichever slave it's for
+            if let Err(e) = conn.reconnect(&mut ctx).await {
+                warn!(
+                    "[{}] can't reconnect after a write timed out: {e}",
+                    conn.addr
+                );
+            }
             return Err(SunSpecCommError::TransientError);
         }
     }
@@ -1388,148 +1098,376 @@ where
 {
     (value.into() as f64) * 10.0_f64.powi(sf.into())
 }
-pub fn get_block_id_from_name(model: &Model, s: &String) -> Option<usize> {
-    for (idx, b) in model.block.iter().enumerate() {
-        if let Some(name) = b.clone().name {
-            if name == *s {
-                return Some(idx);
-            }
-        }
-    }
-    None
-}
+/// Walk a model group, decoding points out of `data` and adding them to the catalog.
+/// Repeated groups are cataloged as `name[1]`, `name[2]`, ...; a group whose count names a point
+/// takes its count from that point in the innermost enclosing group instance that has it, and a
+/// group with a count of 0 repeats until the model's data is used up.
+///
+/// # Arguments
+///
+/// * `data` - the model's registers, starting after ID and L; consumed as points are decoded
+/// * `group` - the group definition to walk
+/// * `prefix` - the catalog path of the enclosing group instance, or None for the root group
+/// * `address` - the register address of the next point; advanced as points are decoded
+/// * `catalog` - the catalog to add decoded points to
 #[async_recursion]
-pub async fn process_json_group(
+pub async fn process_group(
     data: &mut Vec<Word>,
-    group: &Group,
+    group: &ModelGroup,
     prefix: Option<String>,
     address: &mut u16,
     mut catalog: &mut HashMap<String, PointNode>,
-) {
-    let mut entries: i64 = 0;
-    match &group.count {
+) -> Result<(), SunSpecGroupError> {
+    let group_path = format!("{}.{}", prefix.clone().unwrap_or_default(), group.name);
+    let entries: i64 = match &group.count {
         GroupCount::String(countval) => {
-            let count_lookup = match prefix.clone() {
-                Some(s) => format!(".{}.{}", s.split('.').nth(1).unwrap(), countval),
-                None => format!(".{}", countval),
+            let count_path = match find_in_scope(catalog, &group_path, countval) {
+                Some(c) => c,
+                None => {
+                    return Err(SunSpecGroupError::CountUnresolved(format!(
+                        "{countval} for {group_path}"
+                    )));
+                }
             };
-            if let Some(num_of_groups_val) = catalog.get(&count_lookup) {
-                if let ValueType::Integer(num_groups) = num_of_groups_val.value {
-                    entries = num_groups as i64;
+            match catalog.get(&count_path).map(|pn| &pn.value) {
+                Some(ValueType::Integer(num_groups)) if *num_groups >= 0 => *num_groups,
+                _ => {
+                    return Err(SunSpecGroupError::CountUnresolved(format!(
+                        "{count_path} is not a usable count for {group_path}"
+                    )));
                 }
             }
         }
-        GroupCount::Integer(i) => {
-            entries = i.to_i64().unwrap();
-        }
-    }
-    for i in 0..entries {
-        let newprefix = match prefix.clone() {
-            Some(s) => {
-                if entries > 1 {
-                    format!("{s}.{}[{}]", group.name, i + 1)
-                } else {
-                    format!("{s}.{}", group.name)
-                }
-            }
-            None => {
-                if entries > 1 {
-                    format!(".{}[{}]", group.name, i + 1)
-                } else {
-                    format!(".{}", group.name)
-                }
+        GroupCount::Integer(0) => match group.instance_len() {
+            Some(len) if len > 0 => (data.len() / len as usize) as i64,
+            _ => {
+                return Err(SunSpecGroupError::CountUnresolved(format!(
+                    "can't determine the length of {group_path} to fill the model with it"
+                )));
             }
+        },
+        GroupCount::Integer(i) => *i,
+    };
+    for i in 0..entries {
+        let newprefix = if entries > 1 {
+            format!("{group_path}[{}]", i + 1)
+        } else {
+            group_path.clone()
         };
         for p in group.points.iter() {
-            if (p.name == "ID" || p.name == "L") && prefix.clone().is_none() {
+            let size = point_len(p).unwrap_or(0);
+            if (p.id == "ID" || p.id == "L") && prefix.clone().is_none() {
                 // we skip ID and L processing but still increment address
-                *address += p.size as u16;
+                *address += size;
                 continue;
             }
-            let datum: Vec<Word> = data.drain(..p.size as usize).collect();
-            match parse_point_data(&p, &datum) {
+            if data.len() < size as usize {
+                return Err(SunSpecGroupError::InsufficientData(format!(
+                    "{newprefix}.{} needs {size} registers, {} left",
+                    p.id,
+                    data.len()
+                )));
+            }
+            let datum: Vec<Word> = data.drain(..size as usize).collect();
+            match decode_point_value(p, &datum, false) {
                 Ok(v) => {
-                    let pointname = format!("{}.{}", newprefix, p.name);
+                    let pointname = format!("{}.{}", newprefix, p.id);
                     debug!("{}: {} @0x{} {:#?}", group.name, pointname, address, v);
-                    // this is too simple, the actual solution will need to account for
-                    // which group and group number the point belongs to
                     catalog.insert(
                         pointname,
                         PointNode {
                             value: v,
-                            address: address.clone(),
-                            point_data: p.clone().into(),
+                            address: *address,
+                            point_data: p.clone(),
                         },
                     );
                 }
                 Err(e) => {
-                    info!(
-                        "Can't parse point {}.{} {:?}: {e}",
-                        newprefix, p.name, p.type_
-                    );
+                    debug!("Can't decode point {}.{}: {e}", newprefix, p.id);
                 }
             }
-            *address += p.size as u16;
+            *address += size;
         }
         for g in group.groups.iter() {
-            process_json_group(data, g, Some(newprefix.clone()), address, &mut catalog).await;
+            process_group(data, g, Some(newprefix.clone()), address, &mut catalog).await?;
         }
     }
+    Ok(())
 }
-pub fn parse_point_data(p: &crate::json::point::Point, d: &Vec<Word>) -> anyhow::Result<ValueType> {
-    match p.type_ {
-        PointType::Uint16 | PointType::Int16 | PointType::Bitfield16 | PointType::Sunssf => {
-            if let Some(pointval) = d[0].to_i64() {
-                Ok(ValueType::Integer((pointval as i16) as i64))
-            } else {
-                Err(anyhow!("Can't convert to integer"))
-            }
+
+/// Returns the number of registers a point of the given type occupies, or None if the type
+/// doesn't imply a size (strings carry their own length).
+pub fn point_type_size(point_type: &PointType) -> Option<u16> {
+    match point_type {
+        PointType::Int16
+        | PointType::Uint16
+        | PointType::Acc16
+        | PointType::Enum16
+        | PointType::Bitfield16
+        | PointType::Sunssf
+        | PointType::Sf
+        | PointType::Pad
+        | PointType::Count
+        | PointType::Raw16 => Some(1),
+        PointType::Int32
+        | PointType::Uint32
+        | PointType::Acc32
+        | PointType::Enum32
+        | PointType::Bitfield32
+        | PointType::Float32
+        | PointType::Ipaddr => Some(2),
+        PointType::Int64
+        | PointType::Uint64
+        | PointType::Acc64
+        | PointType::Bitfield64
+        | PointType::Float64
+        | PointType::Eui48 => Some(4),
+        PointType::Ipv6addr => Some(8),
+        PointType::String => None,
+    }
+}
+
+/// Returns the number of registers occupied by a point, preferring an explicit length.
+pub fn point_len(point: &Point) -> Option<u16> {
+    point.len.or_else(|| point_type_size(&point.r#type))
+}
+
+/// Starting at the group that contains `path`, look for `name` in each enclosing group, innermost
+/// first, and return the catalog path of the first match.
+pub(crate) fn find_in_scope(
+    catalog: &HashMap<String, PointNode>,
+    path: &str,
+    name: &str,
+) -> Option<String> {
+    let mut scope = match path.rfind('.') {
+        Some(idx) => &path[..idx],
+        None => "",
+    };
+    loop {
+        let candidate = format!("{scope}.{name}");
+        if catalog.contains_key(&candidate) {
+            return Some(candidate);
         }
-        PointType::Enum16 => {
-            if let Some(pointval) = d[0].to_i64() {
-                for s in p.symbols.iter() {
-                    if let Some(symbol_value) = s.value.as_i64() {
-                        if symbol_value == pointval {
-                            return Ok(ValueType::String(s.name.clone()));
-                        }
-                    }
-                }
-                Ok(ValueType::Integer((pointval as i16) as i64))
-            } else {
-                Err(anyhow!("Can't convert to integer"))
-            }
+        match scope.rfind('.') {
+            Some(idx) => scope = &scope[..idx],
+            None => return None,
         }
-        PointType::String => {
-            let bytes: Vec<u8> = d.iter().fold(vec![], |mut x, elem| {
-                let f = elem.to_be_bytes();
-                x.append(&mut f.to_vec());
-                x
-            });
-
-            match String::from_utf8(bytes) {
-                Ok(s) => Ok(ValueType::String(s.trim_matches(char::from(0)).parse()?)),
-                Err(e) => Err(anyhow!("Couldn't parse data to string")),
-            }
+    }
+}
+
+/// Decode the raw registers for a point into a value, following the same rules as get_point
+/// (not-implemented sentinels, accumulators, enum and bitfield symbols) but without applying a
+/// scale factor.
+///
+/// # Arguments
+///
+/// * `point` - The point definition the registers belong to
+/// * `data` - The registers read for this point, starting at the point's address
+/// * `strict_symbol` - whether to error out, rather than synthesize a value, when a symbol is missing
+pub fn decode_point_value(
+    point: &Point,
+    data: &[Word],
+    strict_symbol: bool,
+) -> Result<ValueType, SunSpecReadError> {
+    let needed = point_len(point).unwrap_or(0) as usize;
+    if data.len() < needed {
+        return Err(SunSpecReadError::OtherError(format!(
+            "{}: expected {needed} registers, got {}",
+            point.id,
+            data.len()
+        )));
+    }
+    let data = &data[..needed];
+    match point.r#type {
+        PointType::String => Ok(ValueType::String(decode::string(data)?)),
+        PointType::Int16 | PointType::Sunssf | PointType::Sf => {
+            Ok(ValueType::Integer(decode::int16(data)? as i64))
         }
-        PointType::Uint32 | PointType::Int32 | PointType::Bitfield32 => {
-            let val = (d[0] as u32) << 16 | (d[1] as u32);
-            if val == NOT_IMPLEMENTED_U32 {
-                return Err(anyhow!("Device reports Not implemented"));
-            } else {
-                Ok(ValueType::Integer(val as i64))
-            }
+        PointType::Uint16 | PointType::Count => {
+            Ok(ValueType::Integer(decode::uint16(data)? as i64))
         }
-        PointType::Uint64 => {
-            let val =
-                (d[0] as u64) << 48 | (d[1] as u64) << 32 | (d[2] as u64) << 16 | (d[3] as u64);
-            if val == NOT_IMPLEMENTED_U64 {
-                return Err(anyhow!("Device reports Not implemented"));
+        PointType::Acc16 => Ok(ValueType::Integer(decode::acc16(data)? as i64)),
+        PointType::Raw16 => Ok(ValueType::Integer(decode::raw16(data)? as i64)),
+        PointType::Int32 => Ok(ValueType::Integer(decode::int32(data)? as i64)),
+        PointType::Uint32 => Ok(ValueType::Integer(decode::uint32(data)? as i64)),
+        PointType::Acc32 => Ok(ValueType::Integer(decode::acc32(data)? as i64)),
+        PointType::Int64 => Ok(ValueType::Integer(decode::int64(data)?)),
+        PointType::Uint64 => Ok(ValueType::Integer(decode::uint64(data)? as i64)),
+        PointType::Acc64 => Ok(ValueType::Integer(decode::acc64(data)? as i64)),
+        PointType::Float32 => Ok(ValueType::Float(decode::float32(data)? as f64)),
+        PointType::Float64 => Ok(ValueType::Float(decode::float64(data)?)),
+        PointType::Ipaddr => Ok(ValueType::String(decode::ipaddr(data)?.to_string())),
+        PointType::Ipv6addr => Ok(ValueType::String(decode::ipv6addr(data)?.to_string())),
+        PointType::Eui48 => {
+            let bytes = decode::eui48(data)?.to_be_bytes();
+            let octets: Vec<String> = bytes[2..].iter().map(|b| format!("{b:02x}")).collect();
+            Ok(ValueType::String(octets.join(":")))
+        }
+        PointType::Enum16 | PointType::Enum32 => {
+            let (val, label) = if point.r#type == PointType::Enum16 {
+                (decode::uint16(data)? as u32, "ENUM16")
             } else {
-                Ok(ValueType::Integer(val as i64))
+                (decode::uint32(data)?, "ENUM32")
+            };
+            let Some(symbols) = point.symbol.as_ref() else {
+                return Err(SunSpecReadError::OtherError(String::from(
+                    "An enum was queried but no symbols are present for the point, so can't render.",
+                )));
+            };
+            match symbols
+                .iter()
+                .find(|s| symbol_value(&s.symbol) == Some(val as u64))
+            {
+                Some(s) => Ok(ValueType::String(s.id.clone())),
+                None if strict_symbol => Err(SunSpecReadError::OtherError(format!(
+                    "Enum failure: text symbol doesn't exist for point numeric value (point is {val})"
+                ))),
+                None => Ok(ValueType::String(format!("{label}_{val}"))),
             }
         }
+        PointType::Bitfield16 | PointType::Bitfield32 | PointType::Bitfield64 => {
+            let (val, label) = match point.r#type {
+                PointType::Bitfield16 => (decode::uint16(data)? as u64, "BITFIELD16"),
+                PointType::Bitfield32 => (decode::uint32(data)? as u64, "BITFIELD32"),
+                _ => (decode::uint64(data)?, "BITFIELD64"),
+            };
+            let Some(symbols) = point.symbol.as_ref() else {
+                return if strict_symbol {
+                    Err(SunSpecReadError::OtherError(String::from(
+                        "We tried to parse a bitfield but there aren't symbols for this point.",
+                    )))
+                } else {
+                    Ok(ValueType::String(format!("{label}_{val}")))
+                };
+            };
+            let bv = BitVec::<_, Lsb0>::from_element(val);
+            let values: Vec<String> = symbols
+                .iter()
+                .filter(|s| {
+                    matches!(symbol_value(&s.symbol), Some(bit) if (bit as usize) < bv.len() && bv[bit as usize])
+                })
+                .map(|s| s.id.clone())
+                .collect();
+            Ok(ValueType::Array(values))
+        }
         PointType::Pad => Ok(ValueType::Pad),
-        _ => Err(anyhow!("Point type is not implemented")),
+    }
+}
+
+/// The numeric value of an enum symbol, or the bit number of a bitfield symbol, as written in a
+/// model definition.
+pub(crate) fn symbol_value(symbol: &str) -> Option<u64> {
+    symbol.parse::<u64>().ok()
+}
+
+/// Apply a scale factor to a decoded point value.  Only integers are scaled; enums, bitfields,
+/// strings and floats come back unchanged.
+pub fn scale_value(value: ValueType, sf: i16) -> ValueType {
+    match value {
+        ValueType::Integer(raw) => ValueType::Float(apply_scale_factor(raw as f64, sf)),
+        other => other,
+    }
+}
+
+/// Decoders for the SunSpec point types, from the registers of a single point.  Each one maps the
+/// type's not-implemented value (and, for accumulators, the not-accumulated value) to an error,
+/// so every reader of point data agrees on what a device left out.
+pub mod decode {
+    use super::{
+        SunSpecReadError, Word, NOT_ACCUMULATED_16, NOT_ACCUMULATED_32, NOT_ACCUMULATED_64,
+        NOT_IMPLEMENTED_EUI48, NOT_IMPLEMENTED_I16, NOT_IMPLEMENTED_I32, NOT_IMPLEMENTED_I64,
+        NOT_IMPLEMENTED_U16, NOT_IMPLEMENTED_U32, NOT_IMPLEMENTED_U64,
+    };
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn u32_of(r: &[Word]) -> u32 {
+        ((r[0] as u32) << 16) | r[1] as u32
+    }
+    fn u64_of(r: &[Word]) -> u64 {
+        r.iter().fold(0, |acc, w| (acc << 16) | *w as u64)
+    }
+    fn implemented<T>(value: T, implemented: bool) -> Result<T, SunSpecReadError> {
+        if implemented {
+            Ok(value)
+        } else {
+            Err(SunSpecReadError::DatapointNotImplemented)
+        }
+    }
+    fn accumulated<T: PartialEq>(value: T, not_accumulated: T) -> Result<T, SunSpecReadError> {
+        if value == not_accumulated {
+            Err(SunSpecReadError::OtherError(String::from(
+                "Accumulator datapoint not supported by device (0 value returned)",
+            )))
+        } else {
+            Ok(value)
+        }
+    }
+
+    pub fn int16(r: &[Word]) -> Result<i16, SunSpecReadError> {
+        implemented(r[0] as i16, r[0] != NOT_IMPLEMENTED_I16)
+    }
+    pub fn uint16(r: &[Word]) -> Result<u16, SunSpecReadError> {
+        implemented(r[0], r[0] != NOT_IMPLEMENTED_U16)
+    }
+    /// Raw registers have no not-implemented value.
+    pub fn raw16(r: &[Word]) -> Result<u16, SunSpecReadError> {
+        Ok(r[0])
+    }
+    pub fn acc16(r: &[Word]) -> Result<u16, SunSpecReadError> {
+        accumulated(uint16(r)?, NOT_ACCUMULATED_16)
+    }
+    pub fn int32(r: &[Word]) -> Result<i32, SunSpecReadError> {
+        let v = u32_of(r);
+        implemented(v as i32, v != NOT_IMPLEMENTED_I32)
+    }
+    pub fn uint32(r: &[Word]) -> Result<u32, SunSpecReadError> {
+        let v = u32_of(r);
+        implemented(v, v != NOT_IMPLEMENTED_U32)
+    }
+    pub fn acc32(r: &[Word]) -> Result<u32, SunSpecReadError> {
+        accumulated(uint32(r)?, NOT_ACCUMULATED_32)
+    }
+    pub fn int64(r: &[Word]) -> Result<i64, SunSpecReadError> {
+        let v = u64_of(&r[..4]);
+        implemented(v as i64, v != NOT_IMPLEMENTED_I64)
+    }
+    pub fn uint64(r: &[Word]) -> Result<u64, SunSpecReadError> {
+        let v = u64_of(&r[..4]);
+        implemented(v, v != NOT_IMPLEMENTED_U64)
+    }
+    pub fn acc64(r: &[Word]) -> Result<u64, SunSpecReadError> {
+        accumulated(uint64(r)?, NOT_ACCUMULATED_64)
+    }
+    pub fn float32(r: &[Word]) -> Result<f32, SunSpecReadError> {
+        let v = f32::from_bits(u32_of(r));
+        implemented(v, !v.is_nan())
+    }
+    pub fn float64(r: &[Word]) -> Result<f64, SunSpecReadError> {
+        let v = f64::from_bits(u64_of(&r[..4]));
+        implemented(v, !v.is_nan())
+    }
+    /// A string with its NUL padding removed; an unimplemented string reads as empty.
+    pub fn string(r: &[Word]) -> Result<String, SunSpecReadError> {
+        let bytes: Vec<u8> = r.iter().flat_map(|w| w.to_be_bytes()).collect();
+        match String::from_utf8(bytes) {
+            Ok(s) => Ok(s.trim_matches(char::from(0)).to_string()),
+            Err(e) => Err(SunSpecReadError::OtherError(e.to_string())),
+        }
+    }
+    pub fn ipaddr(r: &[Word]) -> Result<Ipv4Addr, SunSpecReadError> {
+        let v = Ipv4Addr::from(u32_of(r));
+        implemented(v, !v.is_unspecified())
+    }
+    pub fn ipv6addr(r: &[Word]) -> Result<Ipv6Addr, SunSpecReadError> {
+        let mut segments = [0u16; 8];
+        segments.copy_from_slice(&r[..8]);
+        let v = Ipv6Addr::from(segments);
+        implemented(v, !v.is_unspecified())
+    }
+    /// The 48 bit address, from the low bytes of its four registers.
+    pub fn eui48(r: &[Word]) -> Result<u64, SunSpecReadError> {
+        let v = u64_of(&r[..4]) & NOT_IMPLEMENTED_EUI48;
+        implemented(v, v != NOT_IMPLEMENTED_EUI48)
     }
 }
 
@@ -1604,3 +1542,192 @@ fn load_keys(path: &Path, password: Option<&str>) -> io::Result<PrivateKeyDer<'s
         }
     }
 }
+
+#[tokio::test]
+async fn test_process_group_nested_counts() {
+    use crate::json::group::Group;
+    // Outer repeats N times; each Outer instance carries its own count M for Inner
+    let group: ModelGroup = ModelGroup::from(
+        &serde_json::from_str::<Group>(
+            r#"{"name": "T", "type": "group", "points": [
+            {"name": "ID", "type": "uint16", "size": 1},
+            {"name": "L", "type": "uint16", "size": 1},
+            {"name": "N", "type": "uint16", "size": 1}],
+          "groups": [{"name": "Outer", "type": "group", "count": "N",
+            "points": [{"name": "M", "type": "uint16", "size": 1}],
+            "groups": [{"name": "Inner", "type": "group", "count": "M",
+              "points": [{"name": "V", "type": "uint16", "size": 1}]}]}]}"#,
+        )
+        .unwrap(),
+    );
+    let mut data: Vec<Word> = vec![2, 1, 10, 3, 20, 21, 22];
+    let mut catalog: HashMap<String, PointNode> = HashMap::new();
+    process_group(&mut data, &group, None, &mut 40002, &mut catalog)
+        .await
+        .unwrap();
+    assert_eq!(catalog[".T.Outer[1].Inner.V"].value, ValueType::Integer(10));
+    assert_eq!(
+        catalog[".T.Outer[2].Inner[3].V"].value,
+        ValueType::Integer(22)
+    );
+    assert_eq!(catalog[".T.Outer[2].Inner[3].V"].address, 40010);
+
+    let mut data: Vec<Word> = vec![2, 1, 10, 3, 20];
+    let mut catalog: HashMap<String, PointNode> = HashMap::new();
+    let result = process_group(&mut data, &group, None, &mut 40002, &mut catalog).await;
+    assert!(matches!(
+        result,
+        Err(SunSpecGroupError::InsufficientData(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_process_group_count_errors() {
+    use crate::json::group::Group;
+    let group: ModelGroup = ModelGroup::from(
+        &serde_json::from_str::<Group>(
+            r#"{"name": "T", "type": "group", "points": [
+            {"name": "ID", "type": "uint16", "size": 1},
+            {"name": "L", "type": "uint16", "size": 1}],
+          "groups": [{"name": "Missing", "type": "group", "count": "NoSuchPoint",
+            "points": [{"name": "V", "type": "uint16", "size": 1}]}]}"#,
+        )
+        .unwrap(),
+    );
+    let mut catalog: HashMap<String, PointNode> = HashMap::new();
+    let result = process_group(&mut vec![1, 2], &group, None, &mut 40002, &mut catalog).await;
+    assert!(matches!(result, Err(SunSpecGroupError::CountUnresolved(_))));
+
+    // a count of 0 repeats the group until the data runs out
+    let group: ModelGroup = ModelGroup::from(
+        &serde_json::from_str::<Group>(
+            r#"{"name": "T", "type": "group", "points": [
+            {"name": "ID", "type": "uint16", "size": 1},
+            {"name": "L", "type": "uint16", "size": 1}],
+          "groups": [{"name": "Fill", "type": "group", "count": 0,
+            "points": [{"name": "V", "type": "uint16", "size": 1},
+                       {"name": "W", "type": "uint16", "size": 1}]}]}"#,
+        )
+        .unwrap(),
+    );
+    let mut catalog: HashMap<String, PointNode> = HashMap::new();
+    process_group(
+        &mut vec![1, 2, 3, 4, 5, 6],
+        &group,
+        None,
+        &mut 40002,
+        &mut catalog,
+    )
+    .await
+    .unwrap();
+    assert_eq!(catalog[".T.Fill[3].W"].value, ValueType::Integer(6));
+}
+
+#[test]
+fn test_decode_point_value() {
+    let point = |r#type: PointType| Point {
+        r#type,
+        ..Default::default()
+    };
+    assert_eq!(
+        decode_point_value(
+            &point(PointType::Int64),
+            &[0xffff, 0xffff, 0xffff, 0xfffe],
+            false
+        ),
+        Ok(ValueType::Integer(-2))
+    );
+    assert_eq!(
+        decode_point_value(&point(PointType::Int64), &[0x8000, 0, 0, 0], false),
+        Err(SunSpecReadError::DatapointNotImplemented)
+    );
+    assert_eq!(
+        decode_point_value(&point(PointType::Float32), &[0x4148, 0], false),
+        Ok(ValueType::Float(12.5))
+    );
+    assert_eq!(
+        decode_point_value(&point(PointType::Float32), &[0x7fc0, 0], false),
+        Err(SunSpecReadError::DatapointNotImplemented)
+    );
+    assert_eq!(
+        decode_point_value(&point(PointType::Ipaddr), &[0xc0a8, 0x0101], false),
+        Ok(ValueType::String(String::from("192.168.1.1")))
+    );
+    assert!(decode_point_value(&point(PointType::Acc32), &[0, 0], false).is_err());
+    assert_eq!(
+        scale_value(ValueType::Integer(1234), -2),
+        ValueType::Float(12.34)
+    );
+    assert_eq!(
+        scale_value(ValueType::String(String::from("OFF")), -2),
+        ValueType::String(String::from("OFF"))
+    );
+}
+
+#[tokio::test]
+async fn test_read_model_raw() {
+    // after the harness's common model: a vendor model no definition describes, then the end
+    let registers: HashMap<Address, Word> = HashMap::from([
+        (40070, 64999),
+        (40071, 3),
+        (40072, 7),
+        (40073, 8),
+        (40074, 9),
+        (40075, 0xffff),
+        (40076, 0),
+    ]);
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: vec![0],
+            registers,
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    let report = conn
+        .discover_models(&SunSpecData::default(), &DiscoveryLimits::default())
+        .await;
+    assert!(report.complete);
+    assert!(!conn.model(64999).unwrap().is_defined());
+    assert_eq!(
+        conn.read_model_raw(64999).await.unwrap(),
+        vec![64999, 3, 7, 8, 9]
+    );
+    assert!(matches!(
+        conn.read_model_raw(103).await,
+        Err(SunSpecReadError::OtherError(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_populate_models_incomplete_catalog() {
+    // after the harness's common model: model 705 claiming a curve its length has no room for
+    let registers: HashMap<Address, Word> = HashMap::from([
+        (40070, 705),
+        (40071, 13),
+        (40075, 1),
+        (40076, 1),
+        (40085, 0xffff),
+        (40086, 0),
+    ]);
+    let conn = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: vec![0],
+            registers,
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    let err = conn
+        .populate_models(&SunSpecData::default())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Catalog for model 705"), "{err}");
+    // the models are kept all the same
+    assert!(conn.model(705).is_some());
+    assert!(conn.catalog_entry(".DERVoltVar.Ena").is_some());
+}
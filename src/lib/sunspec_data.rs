@@ -1,9 +1,15 @@
 use crate::json::group::Group;
 use crate::json::misc::JSONModel;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
-use crate::sunspec_models::{Model, SunSpecModels, Symbol, ValueType};
-use std::fs::File;
+use crate::model_validation::{
+    validate_json_model, validate_smdx_model, Severity, ValidationIssue,
+};
+use crate::sunspec_models::{Model, ModelOrigin, SmdxFile, SunSpecModels, Symbol};
+use thiserror::Error;
 #[derive(Default, Debug)]
 pub struct ResolvedModel {
     pub model: Model,
@@ -12,130 +18,510 @@ pub struct ResolvedModel {
     pub notes: Option<String>,
 }
 
+/// Environment variable holding extra model directories, separated like `PATH`
+pub const MODEL_PATH_ENV: &str = "SUNSPEC_MODEL_PATH";
+
+#[derive(Error, Debug)]
+pub enum SunSpecDataError {
+    #[error("Couldn't parse model definition: {0}")]
+    ParseError(String),
+    #[error("Definition for model {1} can't be registered as model {0}")]
+    IdMismatch(u16, u16),
+    #[error("Invalid model definition: {}", .0.iter().map(|i| i.to_string()).collect::<Vec<_>>().join("; "))]
+    Invalid(Vec<ValidationIssue>),
+}
+
+/// Models with ids at or above this are looked up per manufacturer
+const VENDOR_MODEL_MIN: u16 = 64200;
+
+/// Manufacturers that share model definitions.  Names are compared case-insensitively.
+const MANUFACTURER_ALIASES: &[&[&str]] = &[&["generac", "pika"]];
+
+/// Returns the vendor directories to search for a manufacturer's models: its own first, then
+/// those of its aliases.
+fn vendor_dirs(manufacturer: &str) -> Vec<String> {
+    let mn = manufacturer.trim().to_ascii_lowercase();
+    let mut dirs: Vec<String> = vec![mn.clone()];
+    for aliases in MANUFACTURER_ALIASES
+        .iter()
+        .filter(|a| a.contains(&mn.as_str()))
+    {
+        dirs.extend(aliases.iter().filter(|a| **a != mn).map(|a| a.to_string()));
+    }
+    dirs
+}
+
+/// Model definitions are cached per model id and, for vendor models, per manufacturer
+type ModelKey = (u16, Option<String>);
+
 /// A struct that holds a hashmap of all known model definitions (SunSpecModels), used as a
-/// library for lazy-loading of data into a model as needed.  Also contains the point catalog/cache.
-#[derive(Default, Debug, Clone)]
+/// library for lazy-loading of data into a model as needed.  Each definition is loaded once;
+/// clones share the cache, so a single SunSpecData can serve many connections and tasks.
+#[derive(Debug, Clone)]
 pub struct SunSpecData {
-    pub models: HashMap<u16, SunSpecModels>,
+    models: Arc<RwLock<HashMap<ModelKey, Arc<SunSpecModels>>>>,
+    /// Directories searched, in order, before the bundled model library.  Each is laid out like
+    /// `models/`: `model_<id>.json` and `smdx_<id>.xml`, with vendor models in `<manufacturer>/`.
+    pub search_paths: Vec<PathBuf>,
+    /// With the `embedded-models` feature, also look in `models/` on disk for definitions that
+    /// weren't compiled in.  Without the feature, models are always read from disk.
+    pub disk_fallback: bool,
+}
+
+/// A library of the directories in SUNSPEC_MODEL_PATH and the bundled models, the same as
+/// `SunSpecData::new(vec![])`.
+impl Default for SunSpecData {
+    fn default() -> Self {
+        SunSpecData::new(vec![])
+    }
 }
 
 impl SunSpecData {
-    /// Load model data from disk
+    /// Create a model library that searches the given directories, then the directories listed
+    /// in SUNSPEC_MODEL_PATH, then the bundled models.  Definitions found earlier in that order
+    /// override later ones.
     ///
     /// # Arguments
     ///
-    /// * `id` - The model id to load from disk.
-    /// * `manufacturer` - The name of the manufacturer, for models >=64200
-    fn load_model_xml(id: u16, manufacturer: &Option<String>) -> anyhow::Result<SunSpecModels> {
-        let filename = match id {
-            f if f >= 64200 => {
-                if let Some(mn) = manufacturer {
-                    format!("models/{}/smdx_{:05}.xml", mn.to_ascii_lowercase(), f)
-                } else {
-                    format!("models/smdx_{:05}.xml", f)
-                }
-            }
-            _ => {
-                format!("models/smdx_{:05}.xml", id)
-            }
-        };
-        let fd = match File::open(filename.clone()) {
-            Ok(f) => f,
-            Err(e) => {
-                anyhow::bail!("Error reading XML file {filename}: {e}");
-            }
-        };
-        let ssm: SunSpecModels = match serde_xml_rs::from_reader(fd) {
-            Ok(m) => m,
+    /// * `search_paths` - Directories of user-supplied model definitions, highest priority first
+    pub fn new(search_paths: Vec<PathBuf>) -> Self {
+        let mut search_paths = search_paths;
+        if let Some(env_paths) = std::env::var_os(MODEL_PATH_ENV) {
+            search_paths
+                .extend(std::env::split_paths(&env_paths).filter(|p| !p.as_os_str().is_empty()));
+        }
+        SunSpecData {
+            models: Arc::default(),
+            search_paths,
+            disk_fallback: false,
+        }
+    }
+    /// Read a file from one of the search paths
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The search path to read from
+    /// * `name` - The path of the file relative to the search path, e.g. `model_1.json`
+    fn read_search_path_file(dir: &Path, name: &str) -> Option<(Cow<'static, [u8]>, ModelOrigin)> {
+        let path = dir.join(name);
+        match std::fs::read(&path) {
+            Ok(data) => Some((Cow::Owned(data), ModelOrigin::File(path))),
             Err(e) => {
-                anyhow::bail!("Couldn't deserialize xml: {e}");
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Can't read model file {}: {e}", path.display());
+                }
+                None
             }
-        };
-        info!(
-            "XML models are deprecated; you likely want to locate the json version of this model."
-        );
-        Ok(ssm)
-    }
-    fn load_model_json(id: u16, manufacturer: &Option<String>) -> anyhow::Result<SunSpecModels> {
-        let filename = format!("models/model_{}.json", id);
-        let fd = match File::open(filename.clone()) {
-            Ok(f) => f,
-            Err(e) => {
-                anyhow::bail!("Error reading JSON file {filename}: {e}");
+        }
+    }
+    /// Read a file from the bundled model library
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The path of the file relative to the models directory, e.g. `model_1.json`
+    fn read_bundled_file(&self, name: &str) -> Option<(Cow<'static, [u8]>, ModelOrigin)> {
+        #[cfg(feature = "embedded-models")]
+        {
+            if let Some(data) = crate::embedded_models::get(name) {
+                return Some((Cow::Borrowed(data), ModelOrigin::Embedded(name.to_string())));
             }
-        };
-        let jsonmodel: JSONModel = match serde_json::from_reader(fd) {
-            Ok(m) => m,
-            Err(e) => {
-                anyhow::bail!("Couldn't deserialize JSON: {e}");
+            if !self.disk_fallback {
+                return None;
             }
+        }
+        SunSpecData::read_search_path_file(Path::new("models"), name)
+    }
+    /// Returns the names of the files that could define a model, most preferred first: vendor
+    /// JSON, vendor SMDX, generic JSON, generic SMDX.  A manufacturer's own definition beats a
+    /// generic one in either format.  Vendor files live in a subdirectory named after the
+    /// manufacturer (or one of its aliases) and are only used for models >= 64200.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The model id to load.
+    /// * `manufacturer` - The name of the manufacturer, for models >=64200
+    fn model_file_names(id: u16, manufacturer: &Option<String>) -> Vec<String> {
+        let vendor_dirs = match (id, manufacturer) {
+            (f, Some(mn)) if f >= VENDOR_MODEL_MIN => vendor_dirs(mn),
+            _ => vec![],
         };
-
-        let ssm = SunSpecModels::from(&jsonmodel);
-        Ok(ssm)
+        let json = format!("model_{}.json", id);
+        let xml = format!("smdx_{:05}.xml", id);
+        let mut names: Vec<String> = vec![];
+        names.extend(vendor_dirs.iter().map(|d| format!("{d}/{json}")));
+        names.extend(vendor_dirs.iter().map(|d| format!("{d}/{xml}")));
+        names.push(json);
+        names.push(xml);
+        names
     }
-    fn load_model(id: u16, manufacturer: Option<String>) -> anyhow::Result<SunSpecModels> {
-        return if let Ok(ssm) = SunSpecData::load_model_json(id, &manufacturer).map_err(|e| {
-            trace!("{e}");
-            e
-        }) {
-            trace!("load_model_json {id}");
-            Ok(ssm)
+    /// Parse a model definition, as JSON or SMDX depending on the file name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the file the data was read from
+    /// * `data` - The contents of the file
+    fn parse_model(name: &str, data: &[u8]) -> anyhow::Result<SunSpecModels> {
+        if name.ends_with(".json") {
+            let jsonmodel: JSONModel = match serde_json::from_slice(data) {
+                Ok(m) => m,
+                Err(e) => {
+                    anyhow::bail!("Couldn't deserialize JSON: {e}");
+                }
+            };
+            Ok(SunSpecModels::from(&jsonmodel))
         } else {
-            trace!("load_model_xml {id}");
-            SunSpecData::load_model_xml(id, &manufacturer)
-        };
+            let ssm: SunSpecModels = match serde_xml_rs::from_reader(data) {
+                Ok(m) => m,
+                Err(e) => {
+                    anyhow::bail!("Couldn't deserialize xml: {e}");
+                }
+            };
+            info!(
+                "XML models are deprecated; you likely want to locate the json version of this model, or convert it with smdx_to_json."
+            );
+            Ok(ssm)
+        }
     }
-    /// retrieve a model definition from the models repository
+    /// Load a model definition, trying each search path and then the bundled library.  Within
+    /// each of those, files are tried in the order model_file_names gives.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The model id to load.
+    /// * `manufacturer` - The name of the manufacturer, for models >=64200
+    fn load_model(&self, id: u16, manufacturer: Option<String>) -> anyhow::Result<SunSpecModels> {
+        let names = SunSpecData::model_file_names(id, &manufacturer);
+        let locations = self.search_paths.iter().map(Some).chain([None]);
+        for location in locations {
+            for name in names.iter() {
+                let file = match location {
+                    Some(dir) => SunSpecData::read_search_path_file(dir, name),
+                    None => self.read_bundled_file(name),
+                };
+                let (data, origin) = match file {
+                    Some(f) => f,
+                    None => continue,
+                };
+                match SunSpecData::parse_model(name, &data) {
+                    Ok(mut ssm) => {
+                        trace!("loaded model {id} from {origin}");
+                        ssm.origin = origin;
+                        return Ok(ssm);
+                    }
+                    Err(e) => {
+                        warn!("Can't load model {id} from {origin}: {e}");
+                    }
+                }
+            }
+        }
+        anyhow::bail!("No definition for model {id} found in {names:?}")
+    }
+    /// Returns the cache key for a model; the manufacturer only matters for vendor models
+    fn model_key(id: u16, mn: &Option<String>) -> ModelKey {
+        match id {
+            f if f >= VENDOR_MODEL_MIN => (f, mn.as_ref().map(|m| m.trim().to_ascii_lowercase())),
+            _ => (id, None),
+        }
+    }
+    /// retrieve a model definition from the models repository, loading it on first use
     ///
     /// # Arguments
     ///
     /// * `model_id` - The model id to load from disk.
+    /// * `mn` - The name of the manufacturer, for models >=64200
     ///
     /// # Returns
     /// Returns a valid SunSpecModels instance, or None if it didn't exist.
-    pub fn get_model(mut self, id: u16, mn: Option<String>) -> Option<SunSpecModels> {
-        let lookup = self.models.get(&id);
-
-        if lookup.is_none() {
-            match SunSpecData::load_model(id, mn) {
-                Ok(m) => {
-                    self.models.insert(id, m.clone());
-                    Some(m)
-                }
-                Err(e) => {
-                    warn!("Can't load model for {id}: {e}");
-                    None
+    pub fn get_model(&self, id: u16, mn: Option<String>) -> Option<Arc<SunSpecModels>> {
+        let key = SunSpecData::model_key(id, &mn);
+        if let Some(model) = self.models.read().unwrap().get(&key) {
+            return Some(model.clone());
+        }
+        match self.load_model(id, mn) {
+            Ok(m) => {
+                // another task may have loaded it meanwhile; keep whichever got there first
+                let mut models = self.models.write().unwrap();
+                Some(models.entry(key).or_insert(Arc::new(m)).clone())
+            }
+            Err(e) => {
+                warn!("Can't load model for {id}: {e}");
+                None
+            }
+        }
+    }
+    /// Load every model definition available from the search paths and the bundled library
+    /// into the cache, so that later lookups never touch the disk.  Returns the number of
+    /// definitions cached.
+    pub fn preload(&self) -> usize {
+        let mut names: Vec<String> = vec![];
+        for dir in self.search_paths.iter() {
+            names.extend(SunSpecData::list_model_files(dir));
+        }
+        #[cfg(feature = "embedded-models")]
+        {
+            names.extend(crate::embedded_models::names().map(String::from));
+        }
+        if cfg!(not(feature = "embedded-models")) || self.disk_fallback {
+            names.extend(SunSpecData::list_model_files(Path::new("models")));
+        }
+        let mut keys: Vec<ModelKey> = names.iter().filter_map(|n| model_key_for_file(n)).collect();
+        keys.sort();
+        keys.dedup();
+        for (id, mn) in keys {
+            self.get_model(id, mn);
+        }
+        self.models.read().unwrap().len()
+    }
+    /// List the model files in a model directory and its manufacturer subdirectories, relative
+    /// to that directory
+    fn list_model_files(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = vec![];
+        let entries = match std::fs::read_dir(dir) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Can't list model directory {}: {e}", dir.display());
+                return names;
+            }
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.path().is_dir() {
+                if let Ok(sub) = std::fs::read_dir(entry.path()) {
+                    for f in sub.flatten() {
+                        names.push(format!("{name}/{}", f.file_name().to_string_lossy()));
+                    }
                 }
+            } else {
+                names.push(name);
             }
-        } else {
-            Some(lookup.unwrap().clone())
         }
+        names
+    }
+    /// Add a JSON model definition to the library, replacing any definition already cached
+    /// for that model and manufacturer.  The definition is validated first and rejected if it
+    /// has any errors.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The model id the definition is for; must match the definition's own id
+    /// * `mn` - The name of the manufacturer, for models >=64200
+    /// * `model` - The model definition
+    pub fn register_json_model(
+        &self,
+        id: u16,
+        mn: Option<String>,
+        model: JSONModel,
+    ) -> Result<Arc<SunSpecModels>, SunSpecDataError> {
+        if model.id != id {
+            return Err(SunSpecDataError::IdMismatch(id, model.id));
+        }
+        check_issues(validate_json_model(&model))?;
+        let mut ssm = SunSpecModels::from(&model);
+        ssm.origin = ModelOrigin::Registered;
+        Ok(self.register(id, mn, ssm))
+    }
+    /// Add an SMDX model definition to the library, replacing any definition already cached
+    /// for that model and manufacturer.  The definition is validated first and rejected if it
+    /// has any errors.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The model id the definition is for; must match the definition's own id
+    /// * `mn` - The name of the manufacturer, for models >=64200
+    /// * `smdx` - The SMDX document
+    pub fn register_smdx_model(
+        &self,
+        id: u16,
+        mn: Option<String>,
+        smdx: &str,
+    ) -> Result<Arc<SunSpecModels>, SunSpecDataError> {
+        let smdx: SmdxFile = match serde_xml_rs::from_str(smdx) {
+            Ok(m) => m,
+            Err(e) => return Err(SunSpecDataError::ParseError(e.to_string())),
+        };
+        if smdx.model.id != id {
+            return Err(SunSpecDataError::IdMismatch(id, smdx.model.id));
+        }
+        check_issues(validate_smdx_model(&smdx))?;
+        let mut ssm = match SunSpecModels::try_from(smdx) {
+            Ok(m) => m,
+            Err(e) => return Err(SunSpecDataError::ParseError(e.to_string())),
+        };
+        ssm.origin = ModelOrigin::Registered;
+        Ok(self.register(id, mn, ssm))
+    }
+    fn register(&self, id: u16, mn: Option<String>, ssm: SunSpecModels) -> Arc<SunSpecModels> {
+        let model = Arc::new(ssm);
+        self.models
+            .write()
+            .unwrap()
+            .insert(SunSpecData::model_key(id, &mn), model.clone());
+        info!("registered model {id} for manufacturer {mn:?}");
+        model
     }
     pub fn get_symbols_for_point(
-        self,
+        &self,
         id: u16,
         point_name: String,
         mn: Option<String>,
     ) -> Option<Vec<Symbol>> {
-        if let Some(model) = self.get_model(id, mn) {
-            // block[0] is always the fixed block, and block[1] will always be
-            // the first repeating block, if exists.
-            let points = &model.model.block[0].point;
-            for point in points.iter() {
-                if point.id == point_name {
-                    return point.symbol.clone();
-                }
-            }
-            if &model.model.block.len() > &1_usize {
-                let points = &model.model.block[1].point;
-                for point in points.iter() {
-                    if point.id == point_name {
-                        return point.symbol.clone();
-                    }
-                }
-            }
-        }
-        None
+        let model = self.get_model(id, mn)?;
+        model.group.find_point(&point_name)?.symbol.clone()
+    }
+}
+
+/// Turn validation errors into a registration error; warnings are only logged
+fn check_issues(issues: Vec<ValidationIssue>) -> Result<(), SunSpecDataError> {
+    let (errors, warnings): (Vec<ValidationIssue>, Vec<ValidationIssue>) = issues
+        .into_iter()
+        .partition(|i| i.severity == Severity::Error);
+    for w in warnings.iter() {
+        warn!("{w}");
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(SunSpecDataError::Invalid(errors))
     }
 }
+
+/// Work out which model a file in a model directory defines, e.g. `generac/smdx_64200.xml` is
+/// model 64200 for manufacturer generac.  Returns None for files that aren't model definitions.
+fn model_key_for_file(name: &str) -> Option<ModelKey> {
+    let (mn, file) = match name.split_once('/') {
+        Some((mn, file)) => (Some(mn.to_string()), file),
+        None => (None, name),
+    };
+    let id = if let Some(id) = file.strip_prefix("model_") {
+        id.strip_suffix(".json")?
+    } else {
+        file.strip_prefix("smdx_")?.strip_suffix(".xml")?
+    };
+    let id: u16 = id.parse().ok()?;
+    Some(SunSpecData::model_key(id, &mn))
+}
+
+#[test]
+fn test_search_path_override() {
+    let dir = std::env::temp_dir().join(format!("sunspec_models_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy("models/smdx_00001.xml", dir.join("smdx_00001.xml")).unwrap();
+
+    // a user directory wins over the bundled json definition, even with a less preferred format
+    let ssd = SunSpecData::new(vec![dir.clone()]);
+    let model = ssd.get_model(1, None).unwrap();
+    assert_eq!(model.origin, ModelOrigin::File(dir.join("smdx_00001.xml")));
+    // models the user directory doesn't have still come from the bundled library
+    let model = SunSpecData::new(vec![dir.clone()])
+        .get_model(103, None)
+        .unwrap();
+    assert!(!matches!(model.origin, ModelOrigin::File(ref p) if p.starts_with(&dir)));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_model_file_precedence() {
+    assert_eq!(
+        SunSpecData::model_file_names(64263, &Some(String::from("Pika"))),
+        vec![
+            "pika/model_64263.json",
+            "generac/model_64263.json",
+            "pika/smdx_64263.xml",
+            "generac/smdx_64263.xml",
+            "model_64263.json",
+            "smdx_64263.xml",
+        ]
+    );
+    // manufacturers only matter for vendor models
+    assert_eq!(
+        SunSpecData::model_file_names(103, &Some(String::from("Pika"))),
+        vec!["model_103.json", "smdx_00103.xml"]
+    );
+
+    let dir = std::env::temp_dir().join(format!("sunspec_vendor_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("generac")).unwrap();
+    std::fs::copy(
+        "models/model_64263.json",
+        dir.join("generac").join("model_64263.json"),
+    )
+    .unwrap();
+    let ssd = SunSpecData::new(vec![dir.clone()]);
+    // an alias finds the vendor json; another manufacturer gets the generic definition
+    let model = ssd.get_model(64263, Some(String::from("PIKA"))).unwrap();
+    assert_eq!(
+        model.origin,
+        ModelOrigin::File(dir.join("generac").join("model_64263.json"))
+    );
+    let model = ssd.get_model(64263, Some(String::from("Other"))).unwrap();
+    assert!(!matches!(model.origin, ModelOrigin::File(ref p) if p.starts_with(&dir)));
+
+    // a manufacturer with only an SMDX definition still gets it over a generic JSON one
+    std::fs::create_dir_all(dir.join("acme")).unwrap();
+    std::fs::copy("models/model_64263.json", dir.join("model_64263.json")).unwrap();
+    std::fs::copy(
+        "models/generac/smdx_64263.xml",
+        dir.join("acme").join("smdx_64263.xml"),
+    )
+    .unwrap();
+    let ssd = SunSpecData::new(vec![dir.clone()]);
+    let model = ssd.get_model(64263, Some(String::from("Acme"))).unwrap();
+    assert_eq!(
+        model.origin,
+        ModelOrigin::File(dir.join("acme").join("smdx_64263.xml"))
+    );
+    let model = ssd.get_model(64263, Some(String::from("Other"))).unwrap();
+    assert_eq!(
+        model.origin,
+        ModelOrigin::File(dir.join("model_64263.json"))
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_shared_model_cache() {
+    let ssd = SunSpecData::default();
+    let shared = ssd.clone();
+    let a = ssd.get_model(103, None).unwrap();
+    let b = shared.get_model(103, None).unwrap();
+    assert!(Arc::ptr_eq(&a, &b));
+    // vendor models are cached per manufacturer
+    assert_eq!(
+        model_key_for_file("generac/smdx_64200.xml"),
+        Some((64200, Some(String::from("generac"))))
+    );
+    assert_eq!(model_key_for_file("smdx_00001.xml"), Some((1, None)));
+    assert_eq!(model_key_for_file("manifest.xml"), None);
+
+    let ssd = SunSpecData::default();
+    assert!(ssd.preload() > 200);
+    assert!(ssd.get_model(64200, Some(String::from("Pika"))).is_some());
+}
+
+#[test]
+fn test_register_model() {
+    let ssd = SunSpecData::default();
+    let smdx = std::fs::read_to_string("models/generac/smdx_64212.xml").unwrap();
+    assert!(matches!(
+        ssd.register_smdx_model(64213, None, &smdx),
+        Err(SunSpecDataError::IdMismatch(64213, 64212))
+    ));
+    ssd.register_smdx_model(64212, Some(String::from("Acme")), &smdx)
+        .unwrap();
+    let model = ssd.get_model(64212, Some(String::from("ACME"))).unwrap();
+    assert_eq!(model.origin, ModelOrigin::Registered);
+    assert_eq!(model.group.groups.len(), 1);
+
+    let broken = smdx.replace(r#"sf="sf_10""#, r#"sf="sf_missing""#);
+    assert!(matches!(
+        ssd.register_smdx_model(64212, None, &broken),
+        Err(SunSpecDataError::Invalid(_))
+    ));
+
+    let json: JSONModel =
+        serde_json::from_slice(&std::fs::read("models/model_64263.json").unwrap()).unwrap();
+    ssd.register_json_model(64263, None, json).unwrap();
+    assert_eq!(
+        ssd.get_model(64263, None).unwrap().origin,
+        ModelOrigin::Registered
+    );
+}
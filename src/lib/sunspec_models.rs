@@ -1,18 +1,20 @@
 use crate::json::defaults::point_access;
-use crate::json::group::{Group, GroupType};
+use crate::json::group::{Group, GroupCount, GroupType};
 use crate::json::misc::JSONModel;
 use crate::json::point;
 use crate::json::point::{
     Point as JSONPoint, PointAccess, PointMandatory, PointSf, PointStatic, PointType as jpt,
     PointValue,
 };
+use crate::sunspec_connection::point_len;
 use crate::{json, sunspec_models};
 use async_recursion::async_recursion;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use std::ops::Deref;
+use std::path::PathBuf;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum ValueType {
     String(String),
     Integer(i64),
@@ -22,7 +24,7 @@ pub enum ValueType {
     Pad,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum Access {
     #[serde(rename = "r")]
     ReadOnly,
@@ -65,6 +67,18 @@ pub enum PointType {
 pub struct Model {
     pub id: u16,
     pub len: u16,
+    /// some vendor SMDX files leave the model unnamed
+    #[serde(default)]
+    pub name: String,
+}
+
+/// The model element of an SMDX file: the model's header and its blocks.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct SmdxModel {
+    pub id: u16,
+    pub len: u16,
+    /// some vendor SMDX files leave the model unnamed
+    #[serde(default)]
     pub name: String,
     pub block: Vec<Block>,
 }
@@ -136,7 +150,7 @@ impl Display for OptionalGroupIdentifier {
 pub struct Point {
     pub id: String,
     pub offset: u16,
-    pub r#type: String,
+    pub r#type: point::PointType,
     pub len: Option<u16>,
     pub mandatory: Option<bool>,
     pub access: Option<Access>,
@@ -149,7 +163,7 @@ pub struct Point {
     pub block_id: Option<u16>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Symbol {
     pub id: String,
     #[serde(rename = "$value")]
@@ -179,7 +193,57 @@ pub struct PointLiteral {
     pub notes: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// The text of a point in an SMDX `strings` element, and of the symbols nested in it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(from = "SmdxPointStrings")]
+pub struct PointStrings {
+    pub id: String,
+    pub label: Option<String>,
+    pub description: Option<String>,
+    pub notes: Option<String>,
+    pub symbol: Vec<SymbolLiteral>,
+}
+
+/// PointStrings as it appears in the file.  serde-xml-rs loses its place in the document after a
+/// sequence of nested elements that isn't the `$value`, so the point's children are read as one.
+#[derive(Deserialize)]
+struct SmdxPointStrings {
+    id: String,
+    #[serde(rename = "$value", default)]
+    children: Vec<PointStringsChild>,
+}
+
+#[derive(Deserialize)]
+enum PointStringsChild {
+    #[serde(rename = "label")]
+    Label(String),
+    #[serde(rename = "description")]
+    Description(String),
+    #[serde(rename = "notes")]
+    Notes(String),
+    #[serde(rename = "symbol")]
+    Symbol(SymbolLiteral),
+}
+
+impl From<SmdxPointStrings> for PointStrings {
+    fn from(smdx: SmdxPointStrings) -> Self {
+        let mut strings = PointStrings {
+            id: smdx.id,
+            ..Default::default()
+        };
+        for child in smdx.children {
+            match child {
+                PointStringsChild::Label(l) => strings.label = Some(l),
+                PointStringsChild::Description(d) => strings.description = Some(d),
+                PointStringsChild::Notes(n) => strings.notes = Some(n),
+                PointStringsChild::Symbol(s) => strings.symbol.push(s),
+            }
+        }
+        strings
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct SymbolLiteral {
     pub(crate) id: String,
     pub(crate) label: Option<String>,
@@ -192,31 +256,428 @@ pub enum LiteralType {
     #[serde(rename = "model")]
     Model(ModelLiteral),
     #[serde(rename = "point")]
-    Point(PointLiteral),
+    Point(PointStrings),
     #[serde(rename = "symbol")]
     Symbol(SymbolLiteral),
 }
 
+/// The human-readable text for a model, point or symbol, in one locale.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LocalizedText {
+    pub label: Option<String>,
+    pub description: Option<String>,
+    pub notes: Option<String>,
+}
+
+impl LocalizedText {
+    /// Empty fields, such as SMDX's `<notes></notes>`, are left out, so they fall back.
+    fn new(label: &Option<String>, description: &Option<String>, notes: &Option<String>) -> Self {
+        let given = |s: &Option<String>| s.clone().filter(|s| !s.is_empty());
+        LocalizedText {
+            label: given(label),
+            description: given(description),
+            notes: given(notes),
+        }
+    }
+
+    /// Fill in the fields this text doesn't have from `other`.
+    fn or(self, other: LocalizedText) -> Self {
+        LocalizedText {
+            label: self.label.or(other.label),
+            description: self.description.or(other.description),
+            notes: self.notes.or(other.notes),
+        }
+    }
+}
+
+/// Whether a `strings` locale matches a requested one.  `exact` requires the whole tag to match,
+/// otherwise the language alone (`de` for `de-AT`) is enough.  Case and `_` versus `-` don't
+/// matter.
+fn locale_matches(locale: &str, wanted: &str, exact: bool) -> bool {
+    let normalize = |l: &str| l.to_ascii_lowercase().replace('_', "-");
+    let (locale, wanted) = (normalize(locale), normalize(wanted));
+    if exact {
+        locale == wanted
+    } else {
+        locale.split('-').next() == wanted.split('-').next()
+    }
+}
+
+impl SunSpecModels {
+    /// The model's string sets in the order they should be searched for `locale`: an exact
+    /// match, then the same language, then English (SMDX strings without a locale are English),
+    /// then any other.
+    fn strings_for(&self, locale: Option<&str>) -> Vec<&Strings> {
+        let wanted = locale.unwrap_or("en");
+        let mut ordered: Vec<&Strings> = vec![];
+        let passes: [&dyn Fn(&Strings) -> bool; 4] = [
+            &|s| locale_matches(s.locale.as_deref().unwrap_or("en"), wanted, true),
+            &|s| locale_matches(s.locale.as_deref().unwrap_or("en"), wanted, false),
+            &|s| locale_matches(s.locale.as_deref().unwrap_or("en"), "en", false),
+            &|_| true,
+        ];
+        for pass in passes.iter() {
+            for s in self.strings.iter().filter(|s| pass(s)) {
+                if !ordered.iter().any(|o| std::ptr::eq(*o, s)) {
+                    ordered.push(s);
+                }
+            }
+        }
+        ordered
+    }
+
+    /// Collect a text from the model's string sets, searched in the order strings_for gives.
+    /// Each field comes from the first set that has it, so a translation that leaves out the
+    /// notes, say, still gets them in English.
+    fn merge_literals(
+        &self,
+        locale: Option<&str>,
+        find: impl Fn(&LiteralType) -> Option<LocalizedText>,
+    ) -> LocalizedText {
+        self.strings_for(locale)
+            .into_iter()
+            .flat_map(|s| s.literals.iter())
+            .filter_map(find)
+            .fold(LocalizedText::default(), LocalizedText::or)
+    }
+
+    /// The model's label, description and notes in `locale` (English if None), falling back
+    /// to English and then to the text in a JSON definition, field by field.
+    pub fn model_text(&self, locale: Option<&str>) -> LocalizedText {
+        let text = self.merge_literals(locale, |l| match l {
+            LiteralType::Model(m) => Some(LocalizedText::new(&m.label, &m.description, &m.notes)),
+            _ => None,
+        });
+        match &self.source {
+            ModelSource::Json(json) => text.or(LocalizedText::new(
+                &json.group.label,
+                &json.group.desc,
+                &json.group.detail,
+            )),
+            _ => text.or(LocalizedText::new(&self.group.label, &None, &None)),
+        }
+    }
+
+    /// A point's label, description and notes in `locale` (English if None), with the same
+    /// fallbacks as model_text.  Returns None if the model has no such point.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - the point's path, as ModelGroup::find_path takes it
+    /// * `locale` - the locale wanted, e.g. `de` or `de-AT`
+    pub fn point_text(&self, point: &str, locale: Option<&str>) -> Option<LocalizedText> {
+        let (_, p) = self.group.find_path(point)?;
+        let text = self.merge_literals(locale, |l| match l {
+            LiteralType::Point(ps) if ps.id == p.id => {
+                Some(LocalizedText::new(&ps.label, &ps.description, &ps.notes))
+            }
+            _ => None,
+        });
+        Some(match &p.literal {
+            Some(l) => text.or(LocalizedText::new(&l.label, &l.description, &l.notes)),
+            None => text,
+        })
+    }
+
+    /// The label, description and notes of one of a point's symbols in `locale` (English if
+    /// None), with the same fallbacks as model_text.  Returns None if the point has no such
+    /// symbol.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - the point's path, as ModelGroup::find_path takes it
+    /// * `symbol` - the symbol's name
+    /// * `locale` - the locale wanted, e.g. `de` or `de-AT`
+    pub fn symbol_text(
+        &self,
+        point: &str,
+        symbol: &str,
+        locale: Option<&str>,
+    ) -> Option<LocalizedText> {
+        let (groups, p) = self.group.find_path(point)?;
+        if !p.symbol.iter().flatten().any(|s| s.id == symbol) {
+            return None;
+        }
+        // SMDX nests symbol strings in their point's; some files list them on their own
+        let text = self.merge_literals(locale, |l| match l {
+            LiteralType::Point(ps) if ps.id == p.id => ps
+                .symbol
+                .iter()
+                .find(|s| s.id == symbol)
+                .map(|s| LocalizedText::new(&s.label, &s.description, &s.notes)),
+            LiteralType::Symbol(s) if s.id == symbol => {
+                Some(LocalizedText::new(&s.label, &s.description, &s.notes))
+            }
+            _ => None,
+        });
+        let json_symbol = match &self.source {
+            ModelSource::Json(json) => find_json_symbol(&json.group, &groups, &p.id, symbol),
+            _ => None,
+        };
+        Some(match json_symbol {
+            Some(s) => text.or(LocalizedText::new(&s.label, &s.desc, &s.detail)),
+            None => text,
+        })
+    }
+}
+
+/// Find a symbol of a JSON definition's point, given the names of the groups nested in `group`
+/// that lead to the point.
+fn find_json_symbol<'a>(
+    group: &'a Group,
+    groups: &[&str],
+    point: &str,
+    symbol: &str,
+) -> Option<&'a json::Symbol> {
+    match groups {
+        [] => group
+            .points
+            .iter()
+            .find(|p| p.name == point)?
+            .symbols
+            .iter()
+            .find(|s| s.name == symbol),
+        [name, rest @ ..] => {
+            let g = group.groups.iter().find(|g| g.name == *name)?;
+            find_json_symbol(g, rest, point, symbol)
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub enum ModelSource {
     #[default]
     XML,
     Json(JSONModel),
+    /// a model found on a device that no definition describes; only its id, length and
+    /// registers are known
+    Undefined,
+}
+
+/// Where a model definition was loaded from, for diagnostics.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub enum ModelOrigin {
+    #[default]
+    Unknown,
+    /// compiled into the crate by the `embedded-models` feature; the path is relative to models/
+    Embedded(String),
+    File(PathBuf),
+    /// registered at runtime through SunSpecData
+    Registered,
+}
+
+impl Display for ModelOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelOrigin::Unknown => write!(f, "unknown"),
+            ModelOrigin::Embedded(name) => write!(f, "embedded:{}", name),
+            ModelOrigin::File(path) => write!(f, "{}", path.display()),
+            ModelOrigin::Registered => write!(f, "registered"),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(try_from = "SmdxFile")]
 pub struct SunSpecModels {
     pub model: Model,
     pub strings: Vec<Strings>,
     #[serde(skip_deserializing)]
     pub source: ModelSource,
+    #[serde(skip_deserializing)]
+    pub origin: ModelOrigin,
+    /// the model definition as a group tree, built from either kind of definition file
+    #[serde(skip)]
+    pub group: ModelGroup,
+}
+
+/// The contents of an SMDX file, before the group tree is built from them.  Deserialising
+/// SunSpecModels from SMDX goes through this, and through the JSON model form, so a definition
+/// that can't be converted fails to load.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct SmdxFile {
+    pub model: SmdxModel,
+    pub strings: Vec<Strings>,
 }
+
+impl TryFrom<SmdxFile> for SunSpecModels {
+    type Error = json::error::ConversionError;
+    fn try_from(smdx: SmdxFile) -> Result<Self, Self::Error> {
+        let json = JSONModel::try_from(&smdx)?;
+        Ok(SunSpecModels {
+            strings: smdx.strings,
+            source: ModelSource::XML,
+            ..SunSpecModels::from(&json)
+        })
+    }
+}
+
+/// One group of a model definition: its points in register order, and the groups nested in it.
+/// JSON and SMDX definitions are both loaded into this form, and point resolution, the point
+/// catalog and the model tree all work from it.
+///
+/// Point offsets are relative to the start of one instance of the group.  The root group starts
+/// at the model's ID register, so ID and L are its first two points.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ModelGroup {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: GroupType,
+    /// how often the group occurs: a fixed number, the name of a count point, or 0 to repeat
+    /// until the model is filled
+    pub count: GroupCount,
+    pub label: Option<String>,
+    pub points: Vec<Point>,
+    pub groups: Vec<ModelGroup>,
+}
+
+impl ModelGroup {
+    fn points_len(&self) -> u16 {
+        self.points.iter().map(|p| point_len(p).unwrap_or(0)).sum()
+    }
+
+    /// Returns the number of registers one instance of this group occupies, or None if that
+    /// depends on the device (a nested group with a variable count).
+    pub fn instance_len(&self) -> Option<u16> {
+        let mut len = self.points_len();
+        for g in self.groups.iter() {
+            match g.count {
+                GroupCount::Integer(count) if count > 0 => {
+                    len += count as u16 * g.instance_len()?
+                }
+                _ => return None,
+            }
+        }
+        Some(len)
+    }
+
+    /// Find a point whose position doesn't depend on the device, either in this group or in a
+    /// nested group that occurs exactly once and follows no repeating group.  Returns the point
+    /// and its offset from the start of this group.
+    pub fn locate(&self, name: &str) -> Option<(&Point, u16)> {
+        if let Some(p) = self.points.iter().find(|p| p.id == name) {
+            return Some((p, p.offset));
+        }
+        let mut offset = self.points_len();
+        for g in self.groups.iter() {
+            if g.count != GroupCount::Integer(1) {
+                return None;
+            }
+            if let Some((p, o)) = g.locate(name) {
+                return Some((p, offset + o));
+            }
+            offset += g.instance_len()?;
+        }
+        None
+    }
+
+    /// Returns the first nested group that doesn't occur exactly once, and its offset from the
+    /// start of this group.
+    pub fn repeating(&self) -> Option<(u16, &ModelGroup)> {
+        let mut offset = self.points_len();
+        for g in self.groups.iter() {
+            if g.count != GroupCount::Integer(1) {
+                return Some((offset, g));
+            }
+            offset += g.instance_len()?;
+        }
+        None
+    }
+
+    /// Find a point by name in this group or any group nested in it.
+    pub fn find_point(&self, name: &str) -> Option<&Point> {
+        self.points
+            .iter()
+            .find(|p| p.id == name)
+            .or_else(|| self.groups.iter().find_map(|g| g.find_point(name)))
+    }
+
+    /// Find a point by its path of group names, starting with this group's: `.DERVoltVar.Crv.Pt.V`,
+    /// or a catalog path such as `.DERVoltVar.Crv[1].Pt[2].V`, whose instance numbers are
+    /// ignored.  A bare name is looked for where PointIdentifier::Point and
+    /// PointIdentifier::Repeating look: in this group, in nested groups that occur once, and in
+    /// the first repeating group.  Returns the names of the nested groups leading to the point,
+    /// and the point.
+    pub fn find_path(&self, path: &str) -> Option<(Vec<&str>, &Point)> {
+        let segments: Vec<&str> = path
+            .trim_start_matches('.')
+            .split('.')
+            .map(|s| s.split('[').next().unwrap_or(s))
+            .collect();
+        match segments[..] {
+            [name] => self.find_unscoped(name),
+            [root, ref rest @ ..] if root == self.name => self.walk_path(rest),
+            _ => None,
+        }
+    }
+
+    fn walk_path(&self, segments: &[&str]) -> Option<(Vec<&str>, &Point)> {
+        match segments {
+            [] => None,
+            [name] => self
+                .points
+                .iter()
+                .find(|p| p.id == *name)
+                .map(|p| (vec![], p)),
+            [group, rest @ ..] => {
+                let g = self.groups.iter().find(|g| g.name == *group)?;
+                let (mut groups, p) = g.walk_path(rest)?;
+                groups.insert(0, g.name.as_str());
+                Some((groups, p))
+            }
+        }
+    }
+
+    fn find_unscoped(&self, name: &str) -> Option<(Vec<&str>, &Point)> {
+        if let Some(p) = self.points.iter().find(|p| p.id == name) {
+            return Some((vec![], p));
+        }
+        for g in self
+            .groups
+            .iter()
+            .filter(|g| g.count == GroupCount::Integer(1))
+        {
+            if let Some((mut groups, p)) = g.find_unscoped(name) {
+                groups.insert(0, g.name.as_str());
+                return Some((groups, p));
+            }
+        }
+        let (_, g) = self.repeating()?;
+        let p = g.points.iter().find(|p| p.id == name)?;
+        Some((vec![g.name.as_str()], p))
+    }
+}
+
+impl From<&Group> for ModelGroup {
+    fn from(group: &Group) -> Self {
+        let mut offset: u16 = 0;
+        let points = group
+            .points
+            .iter()
+            .map(|p| {
+                let mut point = Point::from(p.clone());
+                point.offset = offset;
+                offset = offset.saturating_add(p.size as u16);
+                point
+            })
+            .collect();
+        ModelGroup {
+            name: group.name.clone(),
+            type_: group.type_,
+            count: group.count.clone(),
+            label: group.label.clone(),
+            points,
+            groups: group.groups.iter().map(ModelGroup::from).collect(),
+        }
+    }
+}
+
 impl From<crate::json::point::Point> for Point {
     fn from(value: point::Point) -> Self {
         let mut obj: sunspec_models::Point = Point::default();
         obj.id = value.name;
         obj.len = Some(value.size as u16);
-        obj.r#type = value.type_.to_string();
+        obj.r#type = value.type_;
         obj.mandatory = match value.mandatory {
             PointMandatory::M => Some(true),
             PointMandatory::O => Some(false),
@@ -233,8 +694,7 @@ impl From<crate::json::point::Point> for Point {
                     obj.scale_factor = Some(s);
                 }
                 PointSf::Integer(i) => {
-                    warn!("JSON Point had an integer value for scale factor, we don't know what to do about that.");
-                    obj.scale_factor = None;
+                    obj.scale_factor = Some(i.to_string());
                 }
             },
         }
@@ -245,16 +705,18 @@ impl From<crate::json::point::Point> for Point {
                 PointValue::Integer(i) => Some(ValueType::Integer(i as i64)),
             },
         };
-        let mut literal = PointLiteral::default();
+        let mut literal = PointLiteral {
+            id: obj.id.clone(),
+            ..Default::default()
+        };
         if value.label.is_some() {
             literal.label = value.label.clone();
         }
         if value.desc.is_some() {
             literal.description = value.desc.clone();
         }
-        if value.notes.is_some() {
-            literal.notes = value.notes.clone();
-        }
+        // SMDX notes correspond to the JSON detail
+        literal.notes = value.detail.clone().or(value.notes.clone());
         obj.literal = Some(literal);
 
         obj.symbol = value
@@ -269,8 +731,7 @@ impl From<crate::json::point::Point> for Point {
             })
             .collect();
 
-        // we don't need an offset in a json loadup case, because the catalog point will have the
-        // actual address
+        // the offset depends on where the point sits in its group, which ModelGroup fills in
         obj.offset = 0;
         obj
     }
@@ -278,234 +739,263 @@ impl From<crate::json::point::Point> for Point {
 
 impl From<&JSONModel> for SunSpecModels {
     fn from(json: &JSONModel) -> Self {
-        let mut model_len: u16 = 0;
-        for p in json.group.points.iter() {
-            model_len = model_len.saturating_add(p.size as u16);
-        }
-
-        trace!("JSON Groups count is {:#?}", json.group.count);
-        let mut blocks: Vec<Block> = vec![];
-
-        let mut block_len: u16 = 0;
-        block_len = model_len;
-        let mut points: Vec<Point> = vec![];
-
-        let mut offset: u16 = 0;
-        // in the json model, ID and L are always zero and 1 in the array.  In the xml/smdx,
-        // ID and L aren't specified because they're implied.  Because of this, we skip processing
-        // the first two points so that the offset address lines up.
-        for point in json.group.points.iter().skip(2) {
-            trace!("{}: offset value for {} is {offset}", point.name, json.id);
-
-            let sf_string: Option<String>;
-            if point.sf.is_some() {
-                match point.clone().sf.unwrap() {
-                    PointSf::String(s) => sf_string = Some(s),
-                    PointSf::Integer(i) => sf_string = Some(format!("{}", i)),
-                }
-            } else {
-                sf_string = None;
-            }
-
-            let foo = Point {
-                id: point.name.clone(),
-                offset,
-                r#type: match point.type_ {
-                    jpt::Int16 => "int16".to_string(),
-                    jpt::Int32 => "int32".to_string(),
-                    jpt::Int64 => "int64".to_string(),
-                    jpt::Raw16 => "raw16".to_string(),
-                    jpt::Uint16 => "uint16".to_string(),
-                    jpt::Uint32 => "uint32".to_string(),
-                    jpt::Uint64 => "uint64".to_string(),
-                    jpt::Acc16 => "acc16".to_string(),
-                    jpt::Acc32 => "acc32".to_string(),
-                    jpt::Acc64 => "acc64".to_string(),
-                    jpt::Bitfield16 => "bitfield16".to_string(),
-                    jpt::Bitfield32 => "bitfield32".to_string(),
-                    jpt::Bitfield64 => "bitfield64".to_string(),
-                    jpt::Enum16 => "enum16".to_string(),
-                    jpt::Enum32 => "enum32".to_string(),
-                    jpt::Float32 => "float32".to_string(),
-                    jpt::Float64 => "float64".to_string(),
-                    jpt::String => "string".to_string(),
-                    jpt::Sf => "sf".to_string(),
-                    jpt::Pad => "pad".to_string(),
-                    jpt::Ipaddr => "ipaddr".to_string(),
-                    jpt::Ipv6addr => "ipv6addr".to_string(),
-                    jpt::Eui48 => "eui48".to_string(),
-                    jpt::Sunssf => "sunssf".to_string(),
-                    jpt::Count => "count".to_string(),
-                },
-                len: Some(point.size as u16),
-                mandatory: if point.mandatory == PointMandatory::M {
-                    Some(true)
-                } else {
-                    Some(false)
-                },
-                access: if point.access == PointAccess::Rw {
-                    Some(Access::ReadWrite)
-                } else {
-                    Some(Access::ReadOnly)
-                },
-                symbol: Some(
-                    point
-                        .symbols
-                        .iter()
-                        .map(|s| Symbol {
-                            symbol: s.clone().value.to_string(),
-                            id: s.clone().name,
-                        })
-                        .collect(),
-                ),
-                units: point.units.clone(),
-                scale_factor: sf_string,
-                value: if point.static_ == PointStatic::S {
-                    match point.value.clone() {
-                        Some(PointValue::String(s)) => Some(ValueType::String(s)),
-                        Some(PointValue::Integer(i)) => Some(ValueType::Integer(i as i64)),
-                        None => None,
-                    }
-                } else {
-                    None
-                },
-                literal: None,
-                block_id: None,
-            };
-            points.push(foo);
-            offset = offset.saturating_add(point.size as u16);
-        }
-
-        blocks.push(Block {
-            len: block_len,
-            r#type: None,
-            name: Some(json.group.name.clone()),
-            point: points,
-        });
-
-        if !json.group.groups.is_empty() {
-            // we need to generate a new block per group
-            for g in json.group.groups.iter() {
-                // reinitialize an empty points vec per grouping
-                let mut offset: u16 = 0;
-                let mut points: Vec<Point> = vec![];
-                let mut block = Block::default();
-                block.name = Some(g.clone().name);
-                for point in g.points.iter() {
-                    let sf_string: Option<String>;
-                    if point.sf.is_some() {
-                        match point.clone().sf.unwrap() {
-                            PointSf::String(s) => sf_string = Some(s),
-                            PointSf::Integer(i) => sf_string = Some(format!("{i}")),
-                        }
-                    } else {
-                        sf_string = None;
-                    }
-
-                    let foo = Point {
-                        id: point.name.clone(),
-                        offset,
-                        r#type: match point.type_ {
-                            jpt::Int16 => "int16".to_string(),
-                            jpt::Int32 => "int32".to_string(),
-                            jpt::Int64 => "int64".to_string(),
-                            jpt::Raw16 => "raw16".to_string(),
-                            jpt::Uint16 => "uint16".to_string(),
-                            jpt::Uint32 => "uint32".to_string(),
-                            jpt::Uint64 => "uint64".to_string(),
-                            jpt::Acc16 => "acc16".to_string(),
-                            jpt::Acc32 => "acc32".to_string(),
-                            jpt::Acc64 => "acc64".to_string(),
-                            jpt::Bitfield16 => "bitfield16".to_string(),
-                            jpt::Bitfield32 => "bitfield32".to_string(),
-                            jpt::Bitfield64 => "bitfield64".to_string(),
-                            jpt::Enum16 => "enum16".to_string(),
-                            jpt::Enum32 => "enum32".to_string(),
-                            jpt::Float32 => "float32".to_string(),
-                            jpt::Float64 => "float64".to_string(),
-                            jpt::String => "string".to_string(),
-                            jpt::Sf => "sf".to_string(),
-                            jpt::Pad => "pad".to_string(),
-                            jpt::Ipaddr => "ipaddr".to_string(),
-                            jpt::Ipv6addr => "ipv6addr".to_string(),
-                            jpt::Eui48 => "eui48".to_string(),
-                            jpt::Sunssf => "sunssf".to_string(),
-                            jpt::Count => "count".to_string(),
-                        },
-                        len: Some(point.size as u16),
-                        mandatory: if point.mandatory == PointMandatory::M {
-                            Some(true)
-                        } else {
-                            Some(false)
-                        },
-                        access: if point.access == PointAccess::Rw {
-                            Some(Access::ReadWrite)
-                        } else {
-                            Some(Access::ReadOnly)
-                        },
-                        symbol: Some(
-                            point
-                                .symbols
-                                .iter()
-                                .map(|s| Symbol {
-                                    symbol: s.clone().value.to_string(),
-                                    id: s.clone().name,
-                                })
-                                .collect(),
-                        ),
-                        units: point.units.clone(),
-                        scale_factor: sf_string,
-                        value: if point.static_ == PointStatic::S {
-                            match point.value.clone() {
-                                Some(PointValue::String(s)) => Some(ValueType::String(s)),
-                                Some(PointValue::Integer(i)) => Some(ValueType::Integer(i as i64)),
-                                None => {
-                                    debug!(
-                                "Point is specified as static, but no value provided: {:#?}",
-                                serde_json::to_string(point)
-                            );
-                                    None
-                                }
-                            }
-                        } else {
-                            None
-                        },
-                        literal: None,
-                        block_id: None,
-                    };
-                    debug!(
-                        "{}/{}/{}: offset value is {offset}, size = {}",
-                        json.id, g.name, point.name, point.size
-                    );
-                    points.push(foo);
-                    offset = offset.saturating_add(point.size as u16);
-                }
-                block.point = points;
-                block.len = offset;
-                blocks.push(block);
-            }
-        }
-
+        let group = ModelGroup::from(&json.group);
+        let model_len = group.points_len();
         assert!(model_len > 0);
+
         let model = Model {
             id: json.id,
             len: model_len,
-            name: json.group.clone().name,
-            block: blocks,
+            name: json.group.name.clone(),
         };
 
         SunSpecModels {
             model,
             strings: vec![],
             source: ModelSource::Json(json.clone()),
+            origin: ModelOrigin::default(),
+            group,
+        }
+    }
+}
+
+/// Convert an SMDX model definition into the JSON model form.  The fixed block becomes the root
+/// group, following ID and L, and a repeating block becomes a nested group with a count of 0
+/// (repeat to fill the model).  Gaps between point offsets are filled with pad points, and
+/// labels come from the model's strings, preferring the English ones.
+impl TryFrom<&SmdxFile> for JSONModel {
+    type Error = json::error::ConversionError;
+    fn try_from(smdx: &SmdxFile) -> Result<Self, Self::Error> {
+        let model = &smdx.model;
+        let strings = smdx
+            .strings
+            .iter()
+            .find(|s| s.locale.as_deref().is_none_or(|l| l.starts_with("en")))
+            .or(smdx.strings.first());
+        let literals: &[LiteralType] = match strings {
+            Some(s) => &s.literals,
+            None => &[],
+        };
+        let name = if model.name.is_empty() {
+            format!("model_{}", model.id)
+        } else {
+            model.name.clone()
+        };
+
+        let id_value = Some(PointValue::Integer(model.id as i64));
+        let mut points: Vec<JSONPoint> = vec![
+            smdx_header_point("ID", "Model ID", "Model identifier", id_value),
+            smdx_header_point("L", "Model Length", "Model length", None),
+        ];
+        let mut groups: Vec<Group> = vec![];
+        for (idx, block) in model.block.iter().enumerate() {
+            let block_points = smdx_block_points(block, literals)?;
+            let repeating = block.r#type.as_deref() == Some("repeating");
+            if idx == 0 && !repeating {
+                points.extend(block_points);
+                continue;
+            }
+            groups.push(Group {
+                comments: vec![],
+                count: GroupCount::Integer(if repeating { 0 } else { 1 }),
+                desc: None,
+                detail: None,
+                groups: vec![],
+                label: None,
+                name: block
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| String::from("repeating")),
+                notes: None,
+                points: block_points,
+                type_: GroupType::Group,
+            });
         }
+
+        let mut group = Group {
+            comments: vec![],
+            count: GroupCount::Integer(1),
+            desc: None,
+            detail: None,
+            groups,
+            label: None,
+            name,
+            notes: None,
+            points,
+            type_: GroupType::Group,
+        };
+        for l in literals.iter() {
+            if let LiteralType::Model(ml) = l {
+                group.label = ml.label.clone();
+                group.desc = ml.description.clone();
+                group.detail = ml.notes.clone();
+            }
+        }
+        Ok(JSONModel {
+            id: model.id,
+            group,
+        })
+    }
+}
+
+fn smdx_header_point(name: &str, label: &str, desc: &str, value: Option<PointValue>) -> JSONPoint {
+    JSONPoint {
+        access: PointAccess::R,
+        comments: vec![],
+        count: None,
+        desc: Some(desc.to_string()),
+        detail: None,
+        label: Some(label.to_string()),
+        mandatory: PointMandatory::M,
+        name: name.to_string(),
+        notes: None,
+        sf: None,
+        size: 1,
+        static_: PointStatic::S,
+        symbols: vec![],
+        type_: jpt::Uint16,
+        units: None,
+        value,
+    }
+}
+
+fn smdx_pad_point(name: String) -> JSONPoint {
+    JSONPoint {
+        access: PointAccess::R,
+        comments: vec![],
+        count: None,
+        desc: None,
+        detail: None,
+        label: None,
+        mandatory: PointMandatory::O,
+        name,
+        notes: None,
+        sf: None,
+        size: 1,
+        static_: PointStatic::D,
+        symbols: vec![],
+        type_: jpt::Pad,
+        units: None,
+        value: None,
     }
 }
 
+/// convert the points of an SMDX block in offset order, padding any gaps
+fn smdx_block_points(
+    block: &Block,
+    literals: &[LiteralType],
+) -> Result<Vec<JSONPoint>, json::error::ConversionError> {
+    let mut sorted: Vec<&Point> = block.point.iter().collect();
+    sorted.sort_by_key(|p| p.offset);
+    let mut points: Vec<JSONPoint> = vec![];
+    let mut next_offset: u16 = 0;
+    let mut pads = 0;
+    let mut pad = |points: &mut Vec<JSONPoint>| {
+        pads += 1;
+        points.push(smdx_pad_point(format!("Pad{pads}")));
+    };
+    for p in sorted {
+        if p.offset < next_offset {
+            return Err(format!("point {} overlaps the point before it", p.id).into());
+        }
+        while next_offset < p.offset {
+            pad(&mut points);
+            next_offset += 1;
+        }
+        let type_ = p.r#type;
+        let size = match point_len(p) {
+            Some(s) => s,
+            None => return Err(format!("can't determine the length of point {}", p.id).into()),
+        };
+        let sf = p.scale_factor.as_ref().map(|sf| match sf.parse::<i64>() {
+            Ok(i) => PointSf::Integer(i),
+            Err(_) => PointSf::String(sf.clone()),
+        });
+        let point_literal = literals.iter().find_map(|l| match l {
+            LiteralType::Point(pl) if pl.id == p.id => Some(pl),
+            _ => None,
+        });
+        let symbols = p
+            .symbol
+            .iter()
+            .flatten()
+            .map(|s| {
+                let mut symbol = json::Symbol {
+                    comments: vec![],
+                    desc: None,
+                    detail: None,
+                    label: None,
+                    name: s.id.clone(),
+                    notes: None,
+                    value: match s.symbol.trim().parse::<i64>() {
+                        Ok(v) => serde_json::Value::from(v),
+                        Err(_) => serde_json::Value::from(s.symbol.clone()),
+                    },
+                };
+                // symbol names are only unique within their point, so text nested in the point's
+                // own strings wins over text listed on its own
+                let text = point_literal
+                    .and_then(|pl| pl.symbol.iter().find(|sl| sl.id == s.id))
+                    .or_else(|| {
+                        literals.iter().find_map(|l| match l {
+                            LiteralType::Symbol(sl) if sl.id == s.id => Some(sl),
+                            _ => None,
+                        })
+                    });
+                if let Some(sl) = text {
+                    symbol.label = sl.label.clone();
+                    symbol.desc = sl.description.clone();
+                    symbol.detail = sl.notes.clone();
+                }
+                symbol
+            })
+            .collect();
+        let mut point = JSONPoint {
+            access: match p.access {
+                Some(Access::ReadWrite) => PointAccess::Rw,
+                _ => PointAccess::R,
+            },
+            comments: vec![],
+            count: None,
+            desc: None,
+            detail: None,
+            label: None,
+            mandatory: if p.mandatory == Some(true) {
+                PointMandatory::M
+            } else {
+                PointMandatory::O
+            },
+            name: p.id.clone(),
+            notes: None,
+            sf,
+            size: size as i64,
+            static_: PointStatic::D,
+            symbols,
+            type_,
+            units: p.units.clone(),
+            value: None,
+        };
+        if let Some(pl) = point_literal {
+            point.label = pl.label.clone();
+            point.desc = pl.description.clone();
+            point.detail = pl.notes.clone();
+        }
+        points.push(point);
+        next_offset = p.offset + size;
+    }
+    while next_offset < block.len {
+        pad(&mut points);
+        next_offset += 1;
+    }
+    Ok(points)
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum PointIdentifier {
     Catalog(String),
     Point(String),
+    /// a point inside the repeating block of an SMDX model, by 1-based repeat instance
+    Repeating(u16, String),
 }
 
 impl Display for PointIdentifier {
@@ -513,6 +1003,186 @@ impl Display for PointIdentifier {
         match self {
             PointIdentifier::Catalog(s) => write!(f, "{}", s),
             PointIdentifier::Point(s) => write!(f, "{}", s),
+            PointIdentifier::Repeating(n, s) => write!(f, "{}[{}]", s, n),
         }
     }
 }
+
+#[test]
+fn test_smdx_to_json() {
+    let fd = std::fs::File::open("models/generac/smdx_64212.xml").unwrap();
+    let smdx: SmdxFile = serde_xml_rs::from_reader(fd).unwrap();
+    let json = JSONModel::try_from(&smdx).unwrap();
+    assert_eq!(json.id, 64212);
+    assert_eq!(json.group.name, "DCB105_warranty");
+    // ID and L, then the 12 fixed block points
+    assert_eq!(json.group.points.len(), 14);
+    let module = &json.group.groups[0];
+    assert_eq!(module.name, "DCB105_module");
+    assert_eq!(module.count, GroupCount::Integer(0));
+    assert_eq!(
+        module.points[9].sf,
+        Some(PointSf::String(String::from("sf_10")))
+    );
+    assert!(crate::model_validation::validate_json_model(&json).is_empty());
+
+    // the converted model describes the same registers as the original
+    let back = SunSpecModels::from(&json);
+    assert_eq!(back.group.points[5].id, "NMod");
+    assert_eq!(back.group.points[5].offset, 5);
+
+    // every bundled vendor model converts
+    for entry in std::fs::read_dir("models/generac").unwrap().flatten() {
+        let fd = std::fs::File::open(entry.path()).unwrap();
+        let smdx: SmdxFile = serde_xml_rs::from_reader(fd).unwrap();
+        let json = JSONModel::try_from(&smdx).unwrap();
+        let len: i64 = json.group.points.iter().map(|p| p.size).sum();
+        assert_eq!(
+            len as u16,
+            smdx.model.block[0].len + 2,
+            "{:?}",
+            entry.path()
+        );
+    }
+
+    // a definition that can't be converted fails to load, rather than loading without points
+    let overlapping = r#"<sunSpecModels v="1">
+  <model id="64999" len="2" name="test">
+    <block len="2">
+      <point id="A" offset="0" type="uint32" />
+      <point id="B" offset="1" type="uint16" />
+    </block>
+  </model>
+  <strings id="64999" locale="en" />
+</sunSpecModels>"#;
+    assert!(serde_xml_rs::from_str::<SunSpecModels>(overlapping).is_err());
+
+    // symbol text is nested in its point's strings, and the same symbol name can mean something
+    // else in another point
+    let shared = r#"<sunSpecModels v="1">
+  <model id="64999" len="2" name="test">
+    <block len="2">
+      <point id="Conn" offset="0" type="enum16"><symbol id="ON">1</symbol></point>
+      <point id="Fan" offset="1" type="enum16"><symbol id="ON">1</symbol></point>
+    </block>
+  </model>
+  <strings id="64999" locale="en">
+    <point id="Conn"><label>Connection</label>
+      <symbol id="ON"><label>Connected</label></symbol>
+    </point>
+    <point id="Fan"><label>Fan</label>
+      <symbol id="ON"><label>Running</label></symbol>
+    </point>
+  </strings>
+</sunSpecModels>"#;
+    let smdx: SmdxFile = serde_xml_rs::from_str(shared).unwrap();
+    let json = JSONModel::try_from(&smdx).unwrap();
+    let label = |i: usize| json.group.points[i].symbols[0].label.clone().unwrap();
+    assert_eq!(json.group.points[2].label.as_deref(), Some("Connection"));
+    assert_eq!(label(2), "Connected");
+    assert_eq!(label(3), "Running");
+
+    // as in the bundled definitions
+    let fd = std::fs::File::open("models/smdx_00103.xml").unwrap();
+    let smdx: SmdxFile = serde_xml_rs::from_reader(fd).unwrap();
+    let json = JSONModel::try_from(&smdx).unwrap();
+    let st = json.group.points.iter().find(|p| p.name == "St").unwrap();
+    assert_eq!(st.symbols[0].label.as_deref(), Some("Off"));
+}
+
+#[test]
+fn test_model_group_tree() {
+    // an SMDX model: a 16 register fixed block followed by repeating 16 register module blocks
+    let fd = std::fs::File::open("models/generac/smdx_64212.xml").unwrap();
+    let ssm: SunSpecModels = serde_xml_rs::from_reader(fd).unwrap();
+    let root = &ssm.group;
+    assert_eq!(root.name, "DCB105_warranty");
+    assert_eq!(root.points[0].id, "ID");
+    let (point, offset) = root.locate("NMod").unwrap();
+    assert_eq!((point.r#type, offset), (point::PointType::Uint16, 5));
+    let (offset, module) = root.repeating().unwrap();
+    assert_eq!((offset, module.name.as_str()), (18, "DCB105_module"));
+    assert_eq!(module.instance_len(), Some(16));
+    assert_eq!(module.locate("SOC").unwrap().1, 9);
+    // points in a repeating group have no fixed position in the model
+    assert!(root.locate("SOC").is_none());
+
+    // a JSON model whose repeating group nests another with a device-dependent count
+    let json: JSONModel =
+        serde_json::from_reader(std::fs::File::open("models/model_705.json").unwrap()).unwrap();
+    let ssm = SunSpecModels::from(&json);
+    assert_eq!(ssm.group.locate("Ena").unwrap().1, 2);
+    let (_, curves) = ssm.group.repeating().unwrap();
+    assert_eq!(curves.count, GroupCount::String(String::from("NCrv")));
+    assert_eq!(curves.instance_len(), None);
+    assert!(ssm.group.find_point("V").is_some());
+
+    // every bundled vendor model loads into a tree
+    for dir in ["models/generac", "models/pika"] {
+        for entry in std::fs::read_dir(dir).unwrap().flatten() {
+            let fd = std::fs::File::open(entry.path()).unwrap();
+            let ssm: SunSpecModels = serde_xml_rs::from_reader(fd).unwrap();
+            assert!(ssm.group.points.len() > 2, "{:?}", entry.path());
+        }
+    }
+}
+
+#[test]
+fn test_localized_text() {
+    let smdx = r#"<sunSpecModels v="1">
+  <model id="64999" len="2" name="test">
+    <block len="2">
+      <point id="Mode" offset="0" type="enum16"><symbol id="ON">1</symbol></point>
+      <point id="W" offset="1" type="uint16" />
+    </block>
+  </model>
+  <strings id="64999" locale="en">
+    <model><label>Test</label><description>A test model</description></model>
+    <point id="Mode"><label>Mode</label><description>Operating mode</description></point>
+    <point id="W"><label>Power</label></point>
+    <symbol id="ON"><label>On</label></symbol>
+  </strings>
+  <strings id="64999" locale="de">
+    <model><label>Prüfung</label></model>
+    <point id="Mode"><label>Betriebsart</label></point>
+  </strings>
+</sunSpecModels>"#;
+    let ssm: SunSpecModels = serde_xml_rs::from_reader(smdx.as_bytes()).unwrap();
+    assert_eq!(ssm.model_text(None).label.as_deref(), Some("Test"));
+    assert_eq!(
+        ssm.model_text(Some("de_AT")).label.as_deref(),
+        Some("Prüfung")
+    );
+    let label = |point, locale| ssm.point_text(point, locale).unwrap().label.unwrap();
+    assert_eq!(label("Mode", Some("de")), "Betriebsart");
+    // missing translations fall back to English
+    assert_eq!(label("W", Some("de")), "Power");
+    assert_eq!(label("Mode", Some("fr")), "Mode");
+    // a translation without a description still gets the English one
+    let mode = ssm.point_text(".test.Mode", Some("de")).unwrap();
+    assert_eq!(mode.description.as_deref(), Some("Operating mode"));
+    assert!(ssm.point_text("Nope", None).is_none());
+    let on = ssm.symbol_text("Mode", "ON", Some("de")).unwrap();
+    assert_eq!(on.label.as_deref(), Some("On"));
+    assert!(ssm.symbol_text("Mode", "OFF", None).is_none());
+
+    // JSON definitions have no strings; their own text is used for every locale
+    let json: JSONModel =
+        serde_json::from_reader(std::fs::File::open("models/model_705.json").unwrap()).unwrap();
+    let ssm = SunSpecModels::from(&json);
+    assert_eq!(
+        ssm.model_text(Some("de")).label.as_deref(),
+        Some("DER Volt-Var")
+    );
+    let ena = ssm.symbol_text("VRefAutoEna", "ENABLED", None).unwrap();
+    assert_eq!(ena.label.as_deref(), Some("Enabled Flag"));
+    let vref = ssm.point_text("VRefAutoEna", None).unwrap();
+    assert_eq!(vref.description.as_deref(), Some("Enable autonomous vref."));
+    // Pt's V is only found by its path, not by a point named V anywhere in the model
+    assert!(ssm.point_text("V", None).is_none());
+    let v = ssm.point_text(".DERVoltVar.Crv[1].Pt[2].V", None).unwrap();
+    assert_eq!(v.label.as_deref(), Some("Voltage Point"));
+    assert!(ssm.point_text(".DERVoltVar.Pt.V", None).is_none());
+    let ena = ssm.symbol_text(".DERVoltVar.Ena", "ENABLED", None).unwrap();
+    assert_eq!(ena.label.as_deref(), Some("Enabled"));
+}
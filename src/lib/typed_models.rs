@@ -0,0 +1,193 @@
+//! Strongly typed access to whole models.
+//!
+//! A type implementing [`SunSpecModel`] can decode itself from a model's registers, and be read
+//! from a device with `SunSpecConnection::read_model`.  With the `typed-models` feature, build.rs
+//! generates such a struct for every JSON model in the library, in [`models`]: `Model1`,
+//! `Model103` and so on, with a nested struct per group and an enum per enumerated point.
+//! Scaled points are decoded to `Option<f64>` with their scale factor applied, and every point
+//! the device reports as not implemented is `None`.
+use crate::sunspec_connection::apply_scale_factor;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TypedModelError {
+    #[error("Expected model {0}, but the registers hold model {1}")]
+    WrongModel(u16, u16),
+    #[error("Model needs at least {0} registers, but only {1} were supplied")]
+    ShortBuffer(usize, usize),
+    #[error("Count point {0} isn't implemented, so the group can't be decoded")]
+    Count(&'static str),
+}
+
+pub trait SunSpecModel: Sized {
+    /// The model id this type decodes.
+    const ID: u16;
+
+    /// Decode the model from its registers, starting at the ID register.  Registers past the
+    /// model's own length (from its L register) are ignored.
+    fn decode(regs: &[u16]) -> Result<Self, TypedModelError>;
+}
+
+#[cfg(feature = "typed-models")]
+#[allow(clippy::all, non_camel_case_types)]
+pub mod models {
+    use super::{model_registers, scale, take, Scope, SunSpecModel, TypedModelError};
+    use crate::sunspec_connection::decode;
+    include!(concat!(env!("OUT_DIR"), "/typed_models.rs"));
+}
+
+/// Check the model id and trim `regs` to the model's declared length.
+#[allow(dead_code)]
+pub(crate) fn model_registers(id: u16, regs: &[u16]) -> Result<&[u16], TypedModelError> {
+    if regs.len() < 2 {
+        return Err(TypedModelError::ShortBuffer(2, regs.len()));
+    }
+    if regs[0] != id {
+        return Err(TypedModelError::WrongModel(id, regs[0]));
+    }
+    let len = regs[1] as usize + 2;
+    if regs.len() < len {
+        return Err(TypedModelError::ShortBuffer(len, regs.len()));
+    }
+    Ok(&regs[..len])
+}
+
+/// Take the next `size` registers.
+#[allow(dead_code)]
+pub(crate) fn take<'a>(
+    regs: &'a [u16],
+    pos: &mut usize,
+    size: usize,
+) -> Result<&'a [u16], TypedModelError> {
+    let end = *pos + size;
+    if end > regs.len() {
+        return Err(TypedModelError::ShortBuffer(end, regs.len()));
+    }
+    let data = &regs[*pos..end];
+    *pos = end;
+    Ok(data)
+}
+
+/// The scale factors and counts decoded so far, from the current group and those enclosing it.
+#[derive(Default)]
+#[allow(dead_code)]
+pub(crate) struct Scope(Vec<(&'static str, Option<i64>)>);
+
+#[allow(dead_code)]
+impl Scope {
+    pub(crate) fn mark(&self) -> usize {
+        self.0.len()
+    }
+    pub(crate) fn truncate(&mut self, mark: usize) {
+        self.0.truncate(mark)
+    }
+    pub(crate) fn push(&mut self, name: &'static str, value: Option<i64>) {
+        self.0.push((name, value))
+    }
+    /// Look `name` up, innermost group first.
+    pub(crate) fn get(&self, name: &str) -> Option<i64> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .and_then(|(_, v)| *v)
+    }
+}
+
+/// Apply a scale factor; a value without a usable scale factor can't be trusted either.
+#[allow(dead_code)]
+pub(crate) fn scale(value: Option<f64>, sf: Option<i64>) -> Option<f64> {
+    Some(apply_scale_factor(value?, sf? as i32))
+}
+
+#[test]
+fn test_scope() {
+    assert_eq!(scale(Some(1234.0), Some(-2)), Some(12.34));
+    assert_eq!(scale(Some(1234.0), None), None);
+    assert_eq!(scale(None, Some(-2)), None);
+
+    let mut scope = Scope::default();
+    scope.push("A_SF", Some(-1));
+    let mark = scope.mark();
+    scope.push("A_SF", Some(2));
+    assert_eq!(scope.get("A_SF"), Some(2));
+    scope.truncate(mark);
+    assert_eq!(scope.get("A_SF"), Some(-1));
+
+    assert_eq!(
+        model_registers(1, &[2, 0]),
+        Err(TypedModelError::WrongModel(1, 2))
+    );
+    assert_eq!(model_registers(1, &[1, 1, 5, 6]), Ok(&[1, 1, 5][..]));
+    assert_eq!(
+        model_registers(1, &[1, 4, 5]),
+        Err(TypedModelError::ShortBuffer(6, 3))
+    );
+}
+
+#[cfg(feature = "typed-models")]
+#[test]
+fn test_typed_model() {
+    use crate::sunspec_connection::NOT_IMPLEMENTED_I16;
+    use models::*;
+    // model 1 (common): ID, L, then Mn, Md, Opt, Vr, SN strings, DA and a pad
+    let mut regs: Vec<u16> = vec![1, 66];
+    let mut string = |s: &str, len: usize| {
+        let mut bytes = s.as_bytes().to_vec();
+        bytes.resize(len * 2, 0);
+        regs.extend(bytes.chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]])));
+    };
+    string("Acme", 16);
+    string("Widget", 16);
+    string("", 8);
+    string("1.0", 8);
+    string("SN123", 16);
+    regs.extend([7, 0x8000]);
+    let common = Model1::decode(&regs).unwrap();
+    assert_eq!(common.mn.as_deref(), Some("Acme"));
+    assert_eq!(common.opt, None);
+    assert_eq!(common.da, Some(7));
+
+    // model 103: A=100 with A_SF=-1, St=4 (MPPT), and everything else not implemented
+    let mut regs: Vec<u16> = vec![103, 50];
+    regs.extend([0xffff; 50]);
+    regs[2] = 100;
+    // an unimplemented scale factor leaves the points it scales undecodable
+    regs[6] = NOT_IMPLEMENTED_I16;
+    let inverter = Model103::decode(&regs).unwrap();
+    assert_eq!(inverter.a, None);
+    regs[6] = -1_i16 as u16;
+    regs[38] = 4;
+    let inverter = Model103::decode(&regs).unwrap();
+    assert_eq!(inverter.a, Some(10.0));
+    assert_eq!(inverter.aph_a, None);
+    assert_eq!(inverter.st, Some(Model103St::Mppt));
+    assert_eq!(inverter.evt1, None);
+    assert_eq!(
+        Model103::decode(&regs[..20]),
+        Err(TypedModelError::ShortBuffer(52, 20))
+    );
+}
+
+#[cfg(feature = "typed-models")]
+#[tokio::test]
+async fn test_read_typed_model() {
+    use crate::modbus_test_harness::ModbusTestHarness;
+    use crate::sunspec_connection::SunSpecConnection;
+    use crate::sunspec_data::SunSpecData;
+    use models::Model1;
+
+    let harness = ModbusTestHarness {
+        buf: vec![0],
+        ..Default::default()
+    };
+    let conn = SunSpecConnection::test_new(harness, false).await.unwrap();
+    assert!(conn.read_model::<Model1>().await.is_err());
+    conn.populate_models(&SunSpecData::default()).await.unwrap();
+    let common = conn.read_model::<Model1>().await.unwrap();
+    assert_eq!(common.mn.as_deref(), Some("Test"));
+    assert_eq!(common.md.as_deref(), Some("TestHarness"));
+    assert_eq!(common.opt, None);
+    assert_eq!(common.sn.as_deref(), Some("1234567890"));
+    assert_eq!(common.da, Some(0));
+}
@@ -9,8 +9,11 @@ pub async fn setup(
     manufacturer: String,
     buf_contents: Vec<u16>,
 ) -> (SunSpecConnection, SunSpecData, ModelData) {
-    let meh = ModbusTestHarness { buf: buf_contents };
-    let mut ss = match SunSpecConnection::test_new(meh, false).await {
+    let meh = ModbusTestHarness {
+        buf: buf_contents,
+        ..Default::default()
+    };
+    let ss = match SunSpecConnection::test_new(meh, false).await {
         Ok(mb) => mb,
         Err(e) => {
             panic!("Can't create modbus connection: {e}");
@@ -18,18 +21,15 @@ pub async fn setup(
     };
 
     let ssd = SunSpecData::default();
-    match ss.clone().populate_models(&ssd).await {
-        Ok(m) => ss.models = m,
-        Err(e) => {
-            panic!("Can't populate models: {e}")
-        }
+    if let Err(e) = ss.populate_models(&ssd).await {
+        panic!("Can't populate models: {e}")
     };
-    let model = ssd.clone().get_model(modelid, Some(manufacturer)).unwrap();
+    let model = ssd.get_model(modelid, Some(manufacturer)).unwrap();
     let md = ModelData {
         id: modelid,
         len: model.model.len,
         address: model.model.id,
-        model: model.clone(),
+        model,
         scale_factors: Default::default(),
     };
     (ss, ssd, md)
@@ -1,6 +1,9 @@
-use sunspec_rs::modbus_test_harness::string_to_vec_word;
+use std::collections::HashMap;
+use std::sync::Arc;
+use sunspec_rs::modbus_test_harness::{string_to_vec_word, ModbusTestHarness};
 use sunspec_rs::model_data::ModelData;
-use sunspec_rs::sunspec_models::{PointIdentifier, ValueType};
+use sunspec_rs::sunspec_connection::SunSpecConnection;
+use sunspec_rs::sunspec_models::{PointIdentifier, SunSpecModels, ValueType};
 
 mod common;
 
@@ -16,8 +19,7 @@ pub async fn test_string() {
     let (ss, ssd, md) =
         common::setup(modelid, String::from(field), String::from("Test"), buf).await;
     if let Ok(pt) = ss
-        .clone()
-        .get_point(md.clone(), PointIdentifier::Point(field.to_string()))
+        .get_point(&md, PointIdentifier::Point(field.to_string()))
         .await
     {
         if let Some(val) = pt.value {
@@ -46,8 +48,7 @@ pub async fn test_u16() {
     let (ss, ssd, md) =
         common::setup(modelid, String::from(field), String::from("Pika"), buf).await;
     if let Ok(pt) = ss
-        .clone()
-        .get_point(md.clone(), PointIdentifier::Point(field.to_string()))
+        .get_point(&md, PointIdentifier::Point(field.to_string()))
         .await
     {
         if let Some(val) = pt.value {
@@ -76,8 +77,7 @@ pub async fn test_u32() {
     let (ss, ssd, md) =
         common::setup(modelid, String::from(field), String::from("Generac"), buf).await;
     if let Ok(pt) = ss
-        .clone()
-        .get_point(md.clone(), PointIdentifier::Point(field.to_string()))
+        .get_point(&md, PointIdentifier::Point(field.to_string()))
         .await
     {
         if let Some(val) = pt.value {
@@ -109,8 +109,7 @@ pub async fn test_bitfield16() {
         common::setup(modelid, String::from(field), String::from("Generac"), buf).await;
 
     if let Ok(pt) = ss
-        .clone()
-        .get_point(md.clone(), PointIdentifier::Point(field.to_string()))
+        .get_point(&md, PointIdentifier::Point(field.to_string()))
         .await
     {
         if let Some(val) = pt.value {
@@ -139,8 +138,7 @@ pub async fn test_i32() {
         common::setup(modelid, String::from(field), String::from("Generac"), buf).await;
 
     if let Ok(pt) = ss
-        .clone()
-        .get_point(md.clone(), PointIdentifier::Point(field.to_string()))
+        .get_point(&md, PointIdentifier::Point(field.to_string()))
         .await
     {
         if let Some(val) = pt.value {
@@ -156,3 +154,93 @@ pub async fn test_i32() {
         panic!("No point data returned");
     }
 }
+
+#[tokio::test]
+pub async fn test_repeating_block_address() {
+    let modelid = 64212;
+    let field: &str = "SOC";
+
+    let buf: Vec<u16> = vec![0];
+    let (ss, _ssd, md) =
+        common::setup(modelid, String::from(field), String::from("Generac"), buf).await;
+    // the json definition of this model has no repeating block, so use the smdx one directly
+    let fd = std::fs::File::open("models/generac/smdx_64212.xml").unwrap();
+    let model: SunSpecModels = serde_xml_rs::from_reader(fd).unwrap();
+    // one 16 register fixed block followed by three 16 register module blocks
+    let md = ModelData {
+        len: 64,
+        address: 40100,
+        model: Arc::new(model),
+        ..md
+    };
+    let (_, addr) = ss
+        .resolve_point(&md, &PointIdentifier::Repeating(3, field.to_string()))
+        .unwrap();
+    assert_eq!(addr, 2 + 40100 + 16 + 2 * 16 + 9);
+    let (_, addr) = ss
+        .resolve_point(&md, &PointIdentifier::Repeating(1, field.to_string()))
+        .unwrap();
+    assert_eq!(addr, 2 + 40100 + 16 + 9);
+    assert!(ss
+        .resolve_point(&md, &PointIdentifier::Repeating(4, field.to_string()))
+        .is_err());
+    // points in the fixed block aren't part of any repeat
+    assert!(ss
+        .resolve_point(&md, &PointIdentifier::Repeating(1, "NMod".to_string()))
+        .is_err());
+
+    // a repeating block that carries its own scale factor: each repeat is scaled by its own
+    let smdx = r#"<sunSpecModels v="1">
+  <model id="64999" len="5" name="test">
+    <block len="1">
+      <point id="N" offset="0" type="uint16" />
+    </block>
+    <block len="2" type="repeating" name="module">
+      <point id="V" offset="0" type="uint16" sf="V_SF" />
+      <point id="V_SF" offset="1" type="sunssf" />
+    </block>
+  </model>
+  <strings id="64999" locale="en">
+    <point id="V"><label>Voltage</label></point>
+  </strings>
+</sunSpecModels>"#;
+    let model: SunSpecModels = serde_xml_rs::from_str(smdx).unwrap();
+    let registers: HashMap<u16, u16> = HashMap::from([
+        (40200, 64999),
+        (40201, 5),
+        (40202, 2),
+        (40203, 123),
+        (40204, -1_i16 as u16),
+        (40205, 456),
+        (40206, 1),
+    ]);
+    let ss = SunSpecConnection::test_new(
+        ModbusTestHarness {
+            buf: vec![0],
+            registers,
+            ..Default::default()
+        },
+        false,
+    )
+    .await
+    .unwrap();
+    let md = ModelData {
+        id: 64999,
+        len: 5,
+        address: 40200,
+        model: Arc::new(model),
+        ..md
+    };
+    let value = |n: u16| {
+        let ss = &ss;
+        let md = &md;
+        async move {
+            ss.get_point(md, PointIdentifier::Repeating(n, String::from("V")))
+                .await
+                .unwrap()
+                .value
+        }
+    };
+    assert_eq!(value(1).await, Some(ValueType::Float(12.3)));
+    assert_eq!(value(2).await, Some(ValueType::Float(4560.0)));
+}
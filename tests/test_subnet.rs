@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use sunspec_rs::modbus_test_harness::string_to_vec_word;
+use sunspec_rs::scanner::{ScanOptions, SUNSPEC_MARKER};
+use sunspec_rs::subnet::{sweep_subnet, SweepOptions};
+use sunspec_rs::sunspec_connection::SunSpecConnection;
+use sunspec_rs::sunspec_data::SunSpecData;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Registers of a device with only a common model, at the preferred base.
+fn registers(serial: &str) -> HashMap<u16, u16> {
+    let mut regs: HashMap<u16, u16> = HashMap::new();
+    let mut put = |addr: u16, words: Vec<u16>| {
+        for (i, w) in words.into_iter().enumerate() {
+            regs.insert(addr + i as u16, w);
+        }
+    };
+    put(40000, SUNSPEC_MARKER.to_vec());
+    put(40002, vec![1, 66]);
+    put(40004, string_to_vec_word("Simulator".to_string() + "\0"));
+    put(40020, string_to_vec_word("Sim".to_string() + "\0"));
+    put(40052, string_to_vec_word(serial.to_string()));
+    put(40070, vec![0xffff, 0]);
+    regs
+}
+
+/// A Modbus/TCP server answering function 3 for one unit id, and a gateway exception for any
+/// other unit.  Requests to the slow unit, if any, are answered only after its delay.
+async fn simulate(
+    mut stream: TcpStream,
+    unit_id: u8,
+    regs: HashMap<u16, u16>,
+    slow: Option<(u8, Duration)>,
+) {
+    let mut header = [0_u8; 7];
+    while stream.read_exact(&mut header).await.is_ok() {
+        let len = u16::from_be_bytes([header[4], header[5]]) as usize;
+        let mut pdu = vec![0_u8; len - 1];
+        if stream.read_exact(&mut pdu).await.is_err() {
+            return;
+        }
+        if let Some((_, delay)) = slow.filter(|(unit, _)| *unit == header[6]) {
+            tokio::time::sleep(delay).await;
+        }
+        let reply: Vec<u8> = if header[6] != unit_id {
+            vec![pdu[0] | 0x80, 0x0b]
+        } else if pdu[0] == 0x03 {
+            let addr = u16::from_be_bytes([pdu[1], pdu[2]]);
+            let count = u16::from_be_bytes([pdu[3], pdu[4]]);
+            let mut reply = vec![0x03, (count * 2) as u8];
+            for a in addr..addr + count {
+                reply.extend(regs.get(&a).copied().unwrap_or(0).to_be_bytes());
+            }
+            reply
+        } else {
+            vec![pdu[0] | 0x80, 0x01]
+        };
+        let mut frame = header[..4].to_vec();
+        frame.extend(((reply.len() + 1) as u16).to_be_bytes());
+        frame.push(header[6]);
+        frame.extend(reply);
+        if stream.write_all(&frame).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn serve(
+    listener: TcpListener,
+    unit_id: u8,
+    serial: &'static str,
+    slow: Option<(u8, Duration)>,
+) {
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(simulate(stream, unit_id, registers(serial), slow));
+    }
+}
+
+#[tokio::test]
+pub async fn test_sweep_subnet() {
+    // two simulated devices on loopback addresses of 127.0.0.0/29, sharing a port
+    let first = TcpListener::bind("127.0.0.2:0").await.unwrap();
+    let port = first.local_addr().unwrap().port();
+    let second = TcpListener::bind(("127.0.0.5", port)).await.unwrap();
+    tokio::spawn(serve(first, 1, "SIM0000001", None));
+    tokio::spawn(serve(second, 126, "SIM0000002", None));
+
+    let options = SweepOptions::builder()
+        .ports(vec![port])
+        .connect_timeout(Duration::from_millis(500))
+        .probe_timeout(Duration::from_millis(500))
+        .build();
+    let results = sweep_subnet("127.0.0.0/29", &SunSpecData::default(), &options)
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    let addr = |last: u8| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, last)), port);
+    assert_eq!(results[0].addr, addr(2));
+    assert_eq!(results[1].addr, addr(5));
+
+    let device = &results[0].devices[0];
+    assert_eq!(results[0].devices.len(), 1);
+    assert_eq!(device.slave, 1);
+    assert_eq!(device.base, 40000);
+    assert_eq!(device.models, vec![1]);
+    let identity = device.identity.as_ref().unwrap();
+    assert_eq!(identity.manufacturer, "Simulator");
+    assert_eq!(identity.serial_number, "SIM0000001");
+
+    assert_eq!(results[1].devices[0].slave, 126);
+    assert_eq!(
+        results[1].devices[0]
+            .identity
+            .as_ref()
+            .unwrap()
+            .serial_number,
+        "SIM0000002"
+    );
+}
+
+#[tokio::test]
+pub async fn test_scan_slow_unit() {
+    // unit 2 answers, but only after the probe has given up on it
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(serve(
+        listener,
+        1,
+        "SIM0000003",
+        Some((2, Duration::from_millis(400))),
+    ));
+
+    let conn = SunSpecConnection::new(addr.to_string(), None, false, None)
+        .await
+        .unwrap();
+    let data = SunSpecData::default();
+    let options = ScanOptions::builder()
+        .probe_timeout(Duration::from_millis(200))
+        .build();
+    assert!(conn.scan_slave_ids(&data, [2], &options).await.is_empty());
+    // the late reply to unit 2 isn't taken for unit 1's
+    let results = conn.scan_slave_ids(&data, [1], &options).await;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].base, 40000);
+    assert_eq!(
+        results[0].identity.as_ref().unwrap().serial_number,
+        "SIM0000003"
+    );
+}
+
+/// Accept connections and hold them open without ever answering.
+async fn serve_silent(listener: TcpListener) {
+    let mut streams: Vec<TcpStream> = vec![];
+    while let Ok((stream, _)) = listener.accept().await {
+        streams.push(stream);
+    }
+}
+
+#[tokio::test]
+pub async fn test_sweep_silent_listener() {
+    // a listener that never answers, next to a device on another port of the same host
+    let silent = TcpListener::bind("127.0.0.3:0").await.unwrap();
+    let silent_port = silent.local_addr().unwrap().port();
+    let device = TcpListener::bind("127.0.0.3:0").await.unwrap();
+    let device_port = device.local_addr().unwrap().port();
+    tokio::spawn(serve_silent(silent));
+    tokio::spawn(serve(device, 1, "SIM0000004", None));
+
+    // one task at a time, so the device is only swept once the silent listener gives up
+    let options = SweepOptions::builder()
+        .ports(vec![silent_port, device_port])
+        .concurrency(1)
+        .connect_timeout(Duration::from_millis(200))
+        .probe_timeout(Duration::from_millis(100))
+        .build();
+    let started = Instant::now();
+    let results = sweep_subnet("127.0.0.3", &SunSpecData::default(), &options)
+        .await
+        .unwrap();
+    // the default five unit ids probed in turn, each given up on after the probe timeout
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(500), "{elapsed:?}");
+    assert!(elapsed < Duration::from_secs(5), "{elapsed:?}");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].addr.port(), device_port);
+    assert_eq!(results[0].devices[0].slave, 1);
+}
@@ -15,8 +15,7 @@ pub async fn test_write_string_not_exist() {
     let (ss, _ssd, md) =
         common::setup(modelid, String::from(field), String::from("Generac"), buf).await;
     match ss
-        .clone()
-        .set_point(md.clone(), PointIdentifier::Point(field.to_string()), value)
+        .set_point(&md, PointIdentifier::Point(field.to_string()), value)
         .await
     {
         Ok(_) => {}
@@ -38,8 +37,7 @@ pub async fn test_write_string_read_only() {
     let (ss, _ssd, md) =
         common::setup(modelid, String::from(field), String::from("Generac"), buf).await;
     match ss
-        .clone()
-        .set_point(md.clone(), PointIdentifier::Point(field.to_string()), value)
+        .set_point(&md, PointIdentifier::Point(field.to_string()), value)
         .await
     {
         Ok(_) => {}
@@ -60,8 +58,7 @@ pub async fn test_write_string_wrong_type() {
     let (ss, _ssd, md) =
         common::setup(modelid, String::from(field), String::from("Generac"), buf).await;
     match ss
-        .clone()
-        .set_point(md.clone(), PointIdentifier::Point(field.to_string()), value)
+        .set_point(&md, PointIdentifier::Point(field.to_string()), value)
         .await
     {
         Ok(_) => {}
@@ -82,8 +79,7 @@ pub async fn test_write_enum32_value_too_big() {
     let (ss, _ssd, md) =
         common::setup(modelid, String::from(field), String::from("Generac"), buf).await;
     match ss
-        .clone()
-        .set_point(md.clone(), PointIdentifier::Point(field.to_string()), value)
+        .set_point(&md, PointIdentifier::Point(field.to_string()), value)
         .await
     {
         Ok(_) => {}